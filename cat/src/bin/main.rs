@@ -10,7 +10,8 @@ extern crate clap;
 
 use clap::{Arg, App};
 use cat::*;
-use std::io;
+use std::io::{self, stderr, Write};
+use std::process;
 
 fn main() -> io::Result<()> {
     let matches = App::new("cat").about("Concatenate FILE(s), or standard input, to standard output\nReads from stdin if FILE is -")
@@ -136,8 +137,8 @@ fn main() -> io::Result<()> {
     
     let can_write_fast: bool = !(show_tabs || show_nonprint || show_ends || squeeze_blank || number_mode != NumMode::NumNull);
 
-    let _success = if can_write_fast {
-        write_fast(files).is_ok()
+    let result = if can_write_fast {
+        write_fast(files)
     } else {
         let tab: String = if show_tabs { "^I".to_string() } else { "\t".to_string() };
         let end_of_line: String = if show_ends { "$\n".to_string() } else { "\n".to_string() };
@@ -151,9 +152,19 @@ fn main() -> io::Result<()> {
             tab
         };
 
-        write_lines(files, &options).is_ok()
+        write_lines(files, &options)
     };
 
-    Ok(())
+    match result {
+        Ok(()) => Ok(()),
+        // whoever was reading our output has gone away, so there is no one to tell
+        Err(Errors::Output(ref e)) if e.kind() == io::ErrorKind::BrokenPipe => process::exit(1),
+        Err(e @ Errors::Output(_)) => {
+            writeln!(&mut stderr(), "{}", e)?;
+            process::exit(1);
+        },
+        // the problems themselves have already been reported
+        Err(_) => process::exit(1)
+    }
 }
 
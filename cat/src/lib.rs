@@ -22,17 +22,23 @@
 #[macro_use]
 extern crate quick_error;
 #[cfg(unix)]
+extern crate libc;
+#[cfg(unix)]
 extern crate unix_socket;
 
 use quick_error::ResultExt;
 use std::fs::{metadata, File};
 use std::io::{self, stderr, stdin, stdout, BufWriter, Read, Write};
+#[cfg(unix)]
+use std::mem;
 
 #[cfg(unix)]
 use std::net::Shutdown;
 #[cfg(unix)]
 use std::os::unix::fs::FileTypeExt;
 #[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
 use unix_socket::UnixStream;
 
 /// Numbering Mode.
@@ -69,6 +75,10 @@ quick_error! {
         Directory(p: String) {
             display("cat: {0}: Is a directory", p)
         }
+
+        SameFile(p: String) {
+            display("cat: {0}: input file is output file", p)
+        }
     }
 }
 
@@ -84,7 +94,9 @@ pub struct Options {
 }
 
 pub struct Handle {
-    reader: Box<Read>
+    reader: Box<Read>,
+    #[cfg(unix)]
+    fd: RawFd
 }
 
 /// Recognized file types.
@@ -106,6 +118,9 @@ pub enum Type {
 
 type CatResult<T> = Result<T, Errors>;
 
+#[cfg(target_os = "linux")]
+pub mod splice;
+
 /// Determines file type of input file *path*.
 pub fn get_input_type(path: &str) -> CatResult<Type> {
     if path == "-" {
@@ -142,12 +157,46 @@ pub fn get_input_type(path: &str) -> CatResult<Type> {
     }
 }
 
+/// Whether copying *input* to *output* would read back what has been written,
+/// so that cat would go on until the disk is full. That is when both are the
+/// same regular file, and *input* is short of where *output* writes: the end
+/// of the file if *output* was opened to append, or else its own position.
+#[cfg(unix)]
+pub fn same_file(input: RawFd, output: RawFd) -> bool {
+    let mut in_stat: libc::stat = unsafe { mem::zeroed() };
+    let mut out_stat: libc::stat = unsafe { mem::zeroed() };
+    if unsafe { libc::fstat(input, &mut in_stat) } != 0 || unsafe { libc::fstat(output, &mut out_stat) } != 0 {
+        return false;
+    }
+    let regular = |stat: &libc::stat| stat.st_mode & libc::S_IFMT == libc::S_IFREG;
+    if !regular(&in_stat) || !regular(&out_stat) || in_stat.st_dev != out_stat.st_dev || in_stat.st_ino != out_stat.st_ino {
+        return false;
+    }
+
+    let in_pos = unsafe { libc::lseek(input, 0, libc::SEEK_CUR) };
+    let out_pos = if unsafe { libc::fcntl(output, libc::F_GETFL) } & libc::O_APPEND != 0 {
+        in_stat.st_size
+    } else {
+        unsafe { libc::lseek(output, 0, libc::SEEK_CUR) }
+    };
+    in_pos >= 0 && in_pos < out_pos
+}
+
 /// Opens a file.
-/// Returns a Handle from which a Reader can be accessed, or an error
+/// Returns a Handle from which a Reader can be accessed, or an error.
+/// A regular file that stdout is writing to is refused; see `same_file`.
 pub fn open(path: &str) -> CatResult<Handle> {
     if path == "-" {
         let stdin = stdin();
+        #[cfg(unix)]
+        {
+            if same_file(stdin.as_raw_fd(), stdout().as_raw_fd()) {
+                return Err(Errors::SameFile(path.to_owned()));
+            }
+        }
         return Ok(Handle {
+            #[cfg(unix)]
+            fd: stdin.as_raw_fd(),
             reader: Box::new(stdin) as Box<Read>,
         });
     }
@@ -161,12 +210,21 @@ pub fn open(path: &str) -> CatResult<Handle> {
             let socket = UnixStream::connect(path).context(path)?;
             socket.shutdown(Shutdown::Write).context(path)?;
             Ok(Handle {
+                fd: socket.as_raw_fd(),
                 reader: Box::new(socket) as Box<Read>,
             })
         },
         _ => {
             let file = File::open(path).context(path)?;
+            #[cfg(unix)]
+            {
+                if same_file(file.as_raw_fd(), stdout().as_raw_fd()) {
+                    return Err(Errors::SameFile(path.to_owned()));
+                }
+            }
             Ok(Handle {
+                #[cfg(unix)]
+                fd: file.as_raw_fd(),
                 reader: Box::new(file) as Box<Read>,
             })
         }
@@ -175,7 +233,10 @@ pub fn open(path: &str) -> CatResult<Handle> {
 
 /// Writes files to stdout with no configuration. This allows
 /// a simple memory copy. Returns Ok(()) if no errors
-/// were encountered, or an error with the number of errors encountered
+/// were encountered, or an error with the number of errors encountered.
+/// A failure to write to stdout stops everything, and is returned as it is.
+/// On Linux the kernel is asked to do the copying where it can, and the
+/// buffer is only used for what it can't.
 ///
 /// Takes a vector of file paths as an argument.
 pub fn write_fast(files: Vec<&str>) -> CatResult<()> {
@@ -184,19 +245,32 @@ pub fn write_fast(files: Vec<&str>) -> CatResult<()> {
     let mut error_count = 0;
 
     for file in files {
-        match open(&file) {
-            Ok(mut handle) => while let Ok(n) = handle.reader.read(&mut in_buf) {
+        let result = open(file).and_then(|mut handle| {
+            #[cfg(target_os = "linux")]
+            {
+                writer.flush()?;
+                if splice::copy(handle.fd, writer.as_raw_fd()) {
+                    return Ok(());
+                }
+            }
+            loop {
+                let n = handle.reader.read(&mut in_buf).context(file)?;
                 if n == 0 {
-                    break;
+                    break Ok(());
                 }
-                writer.write_all(&in_buf[..n]).context(&file[..])?;
-            },
+                writer.write_all(&in_buf[..n])?;
+            }
+        });
+        match result {
+            Ok(()) => {},
+            Err(e @ Errors::Output(_)) => return Err(e),
             Err(e) => {
                 writeln!(&mut stderr(), "{}", e)?;
                 error_count += 1;
             }
         }
     }
+    writer.flush()?;
 
     match error_count {
         0 => Ok(()),
@@ -212,7 +286,7 @@ pub struct OutputState {
 
 /// Writes files to stdout with 'options' as configuration. Returns Ok
 /// if no errors were encountered, or an error with the number of
-/// errors encountered.
+/// errors encountered. Like `write_fast`, a write error is returned at once.
 pub fn write_lines(files: Vec<&str>, options: &Options) -> CatResult<()> {
     let mut error_count = 0;
     let mut state = OutputState {
@@ -221,9 +295,13 @@ pub fn write_lines(files: Vec<&str>, options: &Options) -> CatResult<()> {
     };
 
     for file in files {
-        if let Err(e) = write_file_lines(&file, options, &mut state) {
-            writeln!(&mut stderr(), "{}", e).context(&file[..])?;
-            error_count += 1;
+        match write_file_lines(file, options, &mut state) {
+            Ok(()) => {},
+            Err(e @ Errors::Output(_)) => return Err(e),
+            Err(e) => {
+                writeln!(&mut stderr(), "{}", e)?;
+                error_count += 1;
+            }
         }
     }
 
@@ -245,7 +323,8 @@ pub fn write_file_lines(file: &str, options: &Options, state: &mut OutputState)
     let mut writer = BufWriter::with_capacity(1024 * 64, stdout());
     let mut one_blank: bool = false;
 
-    while let Ok(n) = handle.reader.read(&mut in_buf) {
+    loop {
+        let n = handle.reader.read(&mut in_buf).context(file)?;
         if n == 0 {
             break;
         }
@@ -278,11 +357,11 @@ pub fn write_file_lines(file: &str, options: &Options, state: &mut OutputState)
 
             // print to end of line, or buffer
             let offset = if options.show_nonprint {
-                write_nonprint_to_end(&in_buf[pos..], &mut writer, options.tab.as_bytes())
+                write_nonprint_to_end(&in_buf[pos..], &mut writer, options.tab.as_bytes())?
             } else if options.show_tabs {
-                write_tab_to_end(&in_buf[pos..], &mut writer)
+                write_tab_to_end(&in_buf[pos..], &mut writer)?
             } else {
-                write_to_end(&in_buf[pos..], &mut writer)
+                write_to_end(&in_buf[pos..], &mut writer)?
             };
 
             if offset == 0 {
@@ -299,25 +378,26 @@ pub fn write_file_lines(file: &str, options: &Options, state: &mut OutputState)
         }
     }
 
+    writer.flush()?;
     Ok(())
 }
 
 /// Write all symbols until the end of line, or until the end of buffer is reached
 /// Returns the number of written symbols +1, or 0 if the end is reached
-pub fn write_to_end<W: Write>(in_buf: &[u8], writer: &mut W) -> usize {
+pub fn write_to_end<W: Write>(in_buf: &[u8], writer: &mut W) -> io::Result<usize> {
     match in_buf.iter().position(|c| *c == b'\n') {
         Some(p) => {
-            writer.write_all(&in_buf[..p]).unwrap();
-            p + 1
+            writer.write_all(&in_buf[..p])?;
+            Ok(p + 1)
         },
         None => {
-            writer.write_all(in_buf).unwrap();
-            0
+            writer.write_all(in_buf)?;
+            Ok(0)
         }
     }
 }
 
-pub fn write_tab_to_end<W: Write>(mut in_buf: &[u8], writer: &mut W) -> usize {
+pub fn write_tab_to_end<W: Write>(mut in_buf: &[u8], writer: &mut W) -> io::Result<usize> {
     let mut count = 0;
     loop {
         match in_buf
@@ -325,24 +405,24 @@ pub fn write_tab_to_end<W: Write>(mut in_buf: &[u8], writer: &mut W) -> usize {
             .position(|c| *c == b'\n' || *c == b'\t')
         {
             Some(p) => {
-                writer.write_all(&in_buf[..p]).unwrap();
+                writer.write_all(&in_buf[..p])?;
                 if in_buf[p] == b'\n' {
-                    return count + p + 1;
+                    return Ok(count + p + 1);
                 } else {
-                    writer.write_all(b"^I").unwrap();
+                    writer.write_all(b"^I")?;
                     in_buf = &in_buf[p + 1..];
                     count += p + 1;
                 }
             },
             None => {
-                writer.write_all(in_buf).unwrap();
-                return 0;
+                writer.write_all(in_buf)?;
+                return Ok(0);
             }
         };
     }
 }
 
-pub fn write_nonprint_to_end<W: Write>(in_buf: &[u8], writer: &mut W, tab: &[u8]) -> usize {
+pub fn write_nonprint_to_end<W: Write>(in_buf: &[u8], writer: &mut W, tab: &[u8]) -> io::Result<usize> {
     let mut count = 0;
 
     for byte in in_buf.iter().map(|c| *c) {
@@ -357,13 +437,13 @@ pub fn write_nonprint_to_end<W: Write>(in_buf: &[u8], writer: &mut W, tab: &[u8]
             128...159 => writer.write_all(&[b'M', b'-', b'^', byte - 64]),
             160...254 => writer.write_all(&[b'M', b'-', byte - 128]),
             _ => writer.write_all(&[b'M', b'-', b'^', 63]),
-        }.unwrap();
+        }?;
         count += 1;
     }
     if count != in_buf.len() {
-        count + 1
+        Ok(count + 1)
     } else {
-        0
+        Ok(0)
     }
 }
 
@@ -0,0 +1,81 @@
+/*
+ * cat/splice.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+//!
+//! Copying from one file descriptor to another inside the kernel, so the data never has to
+//! pass through our own buffers. Which system call can do it depends on what the two
+//! descriptors are:
+//!
+//! - `copy_file_range` between two regular files
+//! - `splice` when either end is a pipe
+//! - `sendfile` from a regular file or block device to anything else
+//!
+use std::io;
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::ptr;
+
+/// The most any of the calls is asked to move at once. Linux won't move more than this
+/// in a single call anyway.
+const CHUNK: usize = 0x7fff_f000;
+
+/// What a file descriptor is, as far as choosing a system call goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    File,
+    Block,
+    Pipe,
+    Other
+}
+
+fn kind(fd: RawFd) -> Kind {
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut stat) } != 0 {
+        return Kind::Other;
+    }
+    match stat.st_mode & libc::S_IFMT {
+        libc::S_IFREG => Kind::File,
+        libc::S_IFBLK => Kind::Block,
+        libc::S_IFIFO => Kind::Pipe,
+        _ => Kind::Other
+    }
+}
+
+/// Copies everything from *input*'s current position to *output*, using the file positions
+/// of both. Returns true once it is all copied, or false if the kernel couldn't do it for
+/// these descriptors, in which case whatever is left is still there to be read and copied
+/// the ordinary way. Errors are left for the ordinary way to find and report, too.
+///
+/// Some kernels copy nothing from files in /proc and /sys, which report a size of zero, and
+/// say so just as if the end had been reached. Only an end found after copying something is
+/// trusted; an empty file costs one ordinary read to find out it is empty.
+pub fn copy(input: RawFd, output: RawFd) -> bool {
+    let (from, to) = (kind(input), kind(output));
+    let call: fn(RawFd, RawFd) -> isize = if from == Kind::File && to == Kind::File {
+        |input, output| unsafe {
+            libc::copy_file_range(input, ptr::null_mut(), output, ptr::null_mut(), CHUNK, 0)
+        }
+    } else if from == Kind::Pipe || to == Kind::Pipe {
+        |input, output| unsafe {
+            libc::splice(input, ptr::null_mut(), output, ptr::null_mut(), CHUNK, libc::SPLICE_F_MOVE)
+        }
+    } else if from == Kind::File || from == Kind::Block {
+        |input, output| unsafe { libc::sendfile(output, input, ptr::null_mut(), CHUNK) }
+    } else {
+        return false;
+    };
+
+    let mut copied = false;
+    loop {
+        match call(input, output) {
+            0 => return copied,
+            n if n > 0 => copied = true,
+            _ if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {},
+            _ => return false
+        }
+    }
+}
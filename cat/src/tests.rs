@@ -12,6 +12,10 @@ extern crate assert_cli;
 use super::*;
 use crate::{Options, Type, CatResult, get_input_type, open, write_fast};
 use assert_cli::Assert;
+#[cfg(unix)]
+use std::{env, fs, process};
+#[cfg(target_os = "linux")]
+use std::time::Instant;
 
 #[test]
 #[cfg(unix)]
@@ -124,3 +128,124 @@ fn t_read_file() {
     }
 }
 
+
+#[test]
+fn t_missing_file_fails() {
+    Assert::main_binary()
+        .with_args(&["/dev/null", "no-such-file"])
+        .fails_with(1)
+        .stderr().contains("cat: no-such-file: No such file or directory")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["no-such-file", "-"])
+        .stdin("one\n")
+        .fails_with(1)
+        .stdout().is("one")
+        .unwrap();
+}
+
+#[test]
+fn t_write_to_end_errors() {
+    let mut full: &mut [u8] = &mut [0; 2];
+    assert_eq!(write_to_end(b"ab\ncd", &mut full).unwrap(), 3);
+    assert!(write_to_end(b"more", &mut full).is_err());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn t_zero_copy() {
+    let data: Vec<u8> = (0..100_000).map(|i: usize| (i % 251) as u8).collect();
+    let source = env::temp_dir().join(format!("cat-copy-{}", process::id()));
+    let copy = env::temp_dir().join(format!("cat-copy-{}-out", process::id()));
+    fs::write(&source, &data).unwrap();
+
+    // the copy starts from wherever the input has been read up to
+    let mut input = File::open(&source).unwrap();
+    input.read_exact(&mut [0; 10]).unwrap();
+    let output = File::create(&copy).unwrap();
+    assert!(splice::copy(input.as_raw_fd(), output.as_raw_fd()));
+    assert_eq!(fs::read(&copy).unwrap(), &data[10..]);
+    for path in &[source, copy] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn t_zero_copy_proc() {
+    // files in /proc claim to be empty, and some kernels copy nothing from them
+    let copy = env::temp_dir().join(format!("cat-copy-{}-proc", process::id()));
+    let mut input = File::open("/proc/version").unwrap();
+    let mut output = File::create(&copy).unwrap();
+    if !splice::copy(input.as_raw_fd(), output.as_raw_fd()) {
+        io::copy(&mut input, &mut output).unwrap();
+    }
+    assert_eq!(fs::read(&copy).unwrap(), fs::read("/proc/version").unwrap());
+    fs::remove_file(&copy).unwrap();
+}
+
+#[test]
+#[ignore]
+#[cfg(target_os = "linux")]
+fn t_zero_copy_benchmark() {
+    // this writes 96 MiB, so it only runs with --ignored. Add --nocapture to see the times.
+    let data: Vec<u8> = (0..32 * 1024 * 1024).map(|i: usize| (i % 251) as u8).collect();
+    let dir = env::temp_dir();
+    let source = dir.join(format!("cat-bench-{}", process::id()));
+    let kernel = dir.join(format!("cat-bench-{}-kernel", process::id()));
+    let buffered = dir.join(format!("cat-bench-{}-buffered", process::id()));
+    fs::write(&source, &data).unwrap();
+
+    let start = Instant::now();
+    let input = File::open(&source).unwrap();
+    let output = File::create(&kernel).unwrap();
+    assert!(splice::copy(input.as_raw_fd(), output.as_raw_fd()));
+    let kernel_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut input = File::open(&source).unwrap();
+    let mut output = File::create(&buffered).unwrap();
+    let mut in_buf = [0; 1024 * 64];
+    loop {
+        let n = input.read(&mut in_buf).unwrap();
+        if n == 0 {
+            break;
+        }
+        output.write_all(&in_buf[..n]).unwrap();
+    }
+    let buffered_time = start.elapsed();
+
+    println!("32 MiB: zero-copy {:?}, 64 KiB buffer {:?}", kernel_time, buffered_time);
+    assert!(fs::read(&kernel).unwrap() == data);
+    assert!(fs::read(&buffered).unwrap() == data);
+    for path in &[source, kernel, buffered] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn t_same_file() {
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom};
+
+    let path = env::temp_dir().join(format!("cat-same-{}", process::id()));
+    fs::write(&path, b"some text\n").unwrap();
+    let mut input = File::open(&path).unwrap();
+    let other = File::open("Cargo.toml").or_else(|_| File::open("../Cargo.toml")).unwrap();
+
+    // cat f >> f
+    let append = OpenOptions::new().append(true).open(&path).unwrap();
+    assert!(same_file(input.as_raw_fd(), append.as_raw_fd()));
+    assert!(!same_file(other.as_raw_fd(), append.as_raw_fd()));
+    input.seek(SeekFrom::End(0)).unwrap();
+    assert!(!same_file(input.as_raw_fd(), append.as_raw_fd()));
+
+    // cat f 1<>f writes over what has already been read
+    input.seek(SeekFrom::Start(0)).unwrap();
+    let mut write = OpenOptions::new().write(true).open(&path).unwrap();
+    assert!(!same_file(input.as_raw_fd(), write.as_raw_fd()));
+    write.seek(SeekFrom::Start(4)).unwrap();
+    assert!(same_file(input.as_raw_fd(), write.as_raw_fd()));
+    fs::remove_file(&path).unwrap();
+}
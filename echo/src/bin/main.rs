@@ -0,0 +1,94 @@
+/*
+ * echo/main.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+//!
+//! echo - prints strings to stdout
+//!
+//! ```
+//! $ echo hello world
+//! hello world
+//! ```
+//!
+#![allow(unused_assignments)]
+extern crate echo;
+
+use echo::{escaped, Style};
+use std::env;
+use std::io::{self, Write, stdout};
+use std::os::unix::ffi::OsStrExt;
+
+/// Whether *arg* is an option GNU echo would take: a '-' followed by nothing but the letters
+/// n, e and E. Anything else, even '-' on its own, is something to print.
+fn is_option(arg: &[u8]) -> bool {
+    arg.len() > 1 && arg[0] == b'-' && arg[1..].iter().all(|&c| c == b'n' || c == b'e' || c == b'E')
+}
+
+fn usage(name: &str) {
+    println!("USAGE: {} [-neE] [STRING]...", name);
+    println!("  or:  {} --help | --version", name);
+    println!("Print the STRING(s), separated by spaces, then a newline.");
+    println!("   -n  Don\'t print the newline at the end");
+    println!("   -e  Interpret escape sequences in the STRING(s)");
+    println!("   -E  Don\'t interpret escape sequences (the default)");
+    println!("With POSIXLY_CORRECT set, escape sequences are always interpreted, and options are");
+    println!("only looked for when the first argument is -n.");
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<_> = env::args_os().collect();  // Options:
+    let posix = env::var_os("POSIXLY_CORRECT").is_some();
+    let mut escapes: bool = posix;             // Interprets backslashed escape sequences
+    let mut newlines: bool = true;             // Prints newlines
+
+    let stdout = stdout();
+    let mut output = stdout.lock();
+    let mut quit: bool = false;
+
+    // Clap is nice but it's like 200 KB overhead
+    let options = !posix || (args.len() > 1 && args[1] == "-n");
+    if options && args.len() == 2 && args[1] == "--help" {
+        usage(&args[0].to_string_lossy());
+        return Ok(());
+    }
+    if options && args.len() == 2 && args[1] == "--version" {
+        println!("echo {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
+    // only the options before the first string count, and a later letter wins over an earlier one
+    let mut first = 1;
+    while options && first < args.len() && is_option(args[first].as_bytes()) {
+        for c in &args[first].as_bytes()[1..] {
+            match c {
+                b'n' => newlines = false,
+                b'e' => escapes = true,
+                // POSIX echo always interprets escapes
+                _ => escapes = posix
+            }
+        }
+        first += 1;
+    }
+
+    for (i, input) in args[first..].iter().enumerate() {
+        if i > 0 {
+            write!(output, " ")?;
+        }
+        if escapes {
+            quit = escaped(input.as_bytes(), &mut output, Style::Echo)?;
+            if quit {
+                break;
+            }
+        }
+        else { output.write_all(input.as_bytes())?; }
+    }
+    // \c stops everything, the newline too
+    if newlines && !quit {
+        writeln!(output)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,176 @@
+/*
+ * echo/lib.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+//!
+//! The backslash escapes understood by `echo -e`, and by printf in its format string and in
+//! `%b` arguments.
+//!
+use std::char;
+use std::io::{self, ErrorKind, Write};
+
+/// Which escape language to decode. They differ in a few small ways.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    /// `echo -e`: \0NNN and \NNN are octal, and anything unknown or unfinished is printed as it is
+    Echo,
+    /// printf's `%b` arguments: like echo, but \" is a quote, and a bad \x, \u or \U is an error
+    Argument,
+    /// printf's format string: like `%b`, but \NNN is at most three digits even after a 0
+    Format
+}
+
+/// What a single escape sequence stands for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Escape {
+    /// One raw byte
+    Byte(u8),
+    /// A character, to be written in UTF-8
+    Char(char),
+    /// Not an escape after all, so the input is written as it is
+    Literal,
+    /// \c: no more output at all
+    Stop
+}
+
+impl Escape {
+    /// Writes what the escape stands for to *output*. *source* is the escape sequence as it
+    /// was in the input, for when it turns out not to be one.
+    pub fn write<W: Write>(&self, output: &mut W, source: &[u8]) -> io::Result<()> {
+        match *self {
+            Escape::Byte(b) => output.write_all(&[b]),
+            Escape::Char(ch) => output.write_all(ch.encode_utf8(&mut [0; 4]).as_bytes()),
+            Escape::Literal => output.write_all(source),
+            Escape::Stop => Ok(())
+        }
+    }
+}
+
+/// Parse the value of up to *max* hexadecimal or octal digits at the start of *input*, giving
+/// the value and how many digits there were
+pub fn parse(input: &[u8], base: u32, max: usize) -> (u32, usize) {
+    let mut r: u32 = 0;
+    let mut count: usize = 0;
+    for &b in input.iter().take(max) {
+        match (b as char).to_digit(base) {
+            Some(n) => r = r * base + n,
+            None => break
+        }
+        count += 1;
+    }
+    (r, count)
+}
+
+fn error(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Decodes the escape sequence at the start of *input*, which begins with the backslash.
+/// Gives what it stands for and how many bytes of *input* it took up. \0NNN and \xHH are
+/// single raw bytes, \uHHHH and \UHHHHHHHH are characters. The printf styles give an
+/// `InvalidData` error for a \x, \u or \U that GNU printf would refuse.
+pub fn escape(input: &[u8], style: Style) -> io::Result<(Escape, usize)> {
+    let next = match input.get(1) {
+        Some(&n) => n,
+        // a backslash at the very end is just a backslash
+        None => return Ok((Escape::Literal, input.len()))
+    };
+    let rest = &input[2..];
+    /* a list of all the escape codes that can be interpreted */
+    let byte: u8 = match next {
+        b'\\' => b'\\',
+        b'"' if style != Style::Echo => b'"',
+        b'a' => 0x07,
+        b'b' => 0x08,
+        b'c' => return Ok((Escape::Stop, 2)),
+        b'e' => 0x1b,
+        b'f' => 0x0c,
+        b'n' => b'\n',
+        b'r' => b'\r',
+        b't' => b'\t',
+        b'v' => 0x0b,
+        b'x' => {
+            let (value, digits) = parse(rest, 16, 2);
+            if digits == 0 {
+                if style != Style::Echo {
+                    return Err(error("missing hexadecimal number in escape".to_owned()));
+                }
+                return Ok((Escape::Literal, 2));
+            }
+            return Ok((Escape::Byte(value as u8), 2 + digits));
+        },
+        // in a format string, the 0 is one of the three digits
+        b'0'..=b'7' if style == Style::Format => {
+            let (value, digits) = parse(&input[1..], 8, 3);
+            return Ok((Escape::Byte(value as u8), 1 + digits));
+        },
+        // \0 takes up to three more digits, \1 to \7 up to two; too big wraps, like GNU
+        b'0' => {
+            let (value, digits) = parse(rest, 8, 3);
+            return Ok((Escape::Byte(value as u8), 2 + digits));
+        },
+        b'1'..=b'7' => {
+            let (value, digits) = parse(&input[1..], 8, 3);
+            return Ok((Escape::Byte(value as u8), 1 + digits));
+        },
+        b'u' | b'U' => {
+            let want = if next == b'u' { 4 } else { 8 };
+            let (value, digits) = parse(rest, 16, want);
+            let length = 2 + digits;
+            if style == Style::Echo {
+                return match char::from_u32(value) {
+                    Some(ch) if digits == want => Ok((Escape::Char(ch), length)),
+                    _ => Ok((Escape::Literal, 2))
+                };
+            }
+            if digits < want {
+                return Err(error("missing hexadecimal number in escape".to_owned()));
+            }
+            // like C, printf won't spell out most of ASCII this way
+            let allowed = value >= 0xa0 || value == 0x24 || value == 0x40 || value == 0x60;
+            return match char::from_u32(value) {
+                Some(ch) if allowed => Ok((Escape::Char(ch), length)),
+                _ => Err(error(format!("invalid universal character name \\{}{:0width$x}",
+                    next as char, value, width = want)))
+            };
+        },
+        _ => return Ok((Escape::Literal, 2))
+    };
+    Ok((Escape::Byte(byte), 2))
+}
+
+/// Parse string with escape sequences in the given *style*, and write it to *output*. Returns
+/// whether \c asked for no more output.
+pub fn escaped<W: Write>(input: &[u8], output: &mut W, style: Style) -> io::Result<bool> {
+    let mut i: usize = 0;
+
+    while i < input.len() {
+        let plain = input[i..].iter().position(|&b| b == b'\\').unwrap_or(input.len() - i);
+        output.write_all(&input[i..i + plain])?;
+        i += plain;
+        if i == input.len() {
+            break;
+        }
+        let (decoded, length) = escape(&input[i..], style)?;
+        if decoded == Escape::Stop {
+            return Ok(true);
+        }
+        decoded.write(output, &input[i..i + length])?;
+        i += length;
+    }
+
+    Ok(false)
+}
+
+/// Like `escaped()`, but gives the decoded string, and whether \c cut it short.
+pub fn decode(input: &[u8], style: Style) -> io::Result<(Vec<u8>, bool)> {
+    let mut output: Vec<u8> = Vec::with_capacity(input.len());
+    let stop = escaped(input, &mut output, style)?;
+    Ok((output, stop))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,144 @@
+/*
+ * echo/tests.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+extern crate assert_cli;
+
+use crate::*;
+use assert_cli::{Assert, Environment};
+
+fn echo(input: &str) -> Vec<u8> {
+    decode(input.as_bytes(), Style::Echo).unwrap().0
+}
+
+#[test]
+fn t_simple_escapes() {
+    assert_eq!(echo("\\\\"), b"\\");
+    assert_eq!(echo("\\a"), b"\x07");
+    assert_eq!(echo("\\b"), b"\x08");
+    assert_eq!(echo("\\e"), b"\x1b");
+    assert_eq!(echo("\\f"), b"\x0c");
+    assert_eq!(echo("\\n"), b"\n");
+    assert_eq!(echo("\\r"), b"\r");
+    assert_eq!(echo("\\t"), b"\t");
+    assert_eq!(echo("\\v"), b"\x0b");
+    assert_eq!(echo("a\\tb\\nc"), b"a\tb\nc");
+    // not escapes at all
+    assert_eq!(echo("\\q\\\"\\ "), b"\\q\\\"\\ ");
+    assert_eq!(echo("no escapes"), b"no escapes");
+}
+
+#[test]
+fn t_octal() {
+    assert_eq!(echo("\\0101"), b"A");
+    assert_eq!(echo("\\101"), b"A");
+    assert_eq!(echo("\\01011"), b"A1");
+    assert_eq!(echo("\\1011"), b"A1");
+    assert_eq!(echo("\\0"), b"\0");
+    assert_eq!(echo("\\08"), b"\x008");
+    assert_eq!(echo("\\18"), b"\x018");
+    assert_eq!(echo("\\0377"), b"\xff");
+    // too big for a byte wraps around
+    assert_eq!(echo("\\0400"), b"\0");
+    assert_eq!(echo("\\777"), b"\xff");
+}
+
+#[test]
+fn t_hexadecimal() {
+    assert_eq!(echo("\\x41"), b"A");
+    assert_eq!(echo("\\x4"), b"\x04");
+    assert_eq!(echo("\\x414"), b"A4");
+    assert_eq!(echo("\\xff\\xFe"), b"\xff\xfe");
+    assert_eq!(echo("\\xg"), b"\\xg");
+    assert_eq!(echo("\\x"), b"\\x");
+}
+
+#[test]
+fn t_unicode() {
+    assert_eq!(echo("\\u00e9"), "é".as_bytes());
+    assert_eq!(echo("\\U0001F600"), "\u{1F600}".as_bytes());
+    assert_eq!(echo("\\u41"), b"\\u41");
+    assert_eq!(echo("\\ud800"), b"\\ud800");
+    assert_eq!(echo("\\U00110000"), b"\\U00110000");
+}
+
+#[test]
+fn t_stop_and_trailing_backslash() {
+    assert_eq!(decode(b"ab\\cde", Style::Echo).unwrap(), (b"ab".to_vec(), true));
+    assert_eq!(decode(b"\\c", Style::Echo).unwrap(), (Vec::new(), true));
+    assert_eq!(decode(b"ab\\", Style::Echo).unwrap(), (b"ab\\".to_vec(), false));
+    assert_eq!(decode(b"\\\\\\", Style::Echo).unwrap(), (b"\\\\".to_vec(), false));
+    let mut output: Vec<u8> = Vec::new();
+    assert!(escaped(b"x\\cy", &mut output, Style::Echo).unwrap());
+    assert_eq!(output, b"x");
+}
+
+#[test]
+fn t_printf_styles() {
+    assert_eq!(decode(b"\\0101", Style::Format).unwrap().0, b"\x081");
+    assert_eq!(decode(b"\\0101", Style::Argument).unwrap().0, b"A");
+    assert_eq!(decode(b"\\\"", Style::Argument).unwrap().0, b"\"");
+    assert_eq!(escape(b"\\x41", Style::Format).unwrap(), (Escape::Byte(b'A'), 4));
+    assert_eq!(escape(b"\\u00e9", Style::Format).unwrap(), (Escape::Char('é'), 6));
+    assert!(escape(b"\\xg", Style::Format).is_err());
+    assert!(escape(b"\\u12", Style::Argument).is_err());
+    assert!(escape(b"\\u0041", Style::Format).is_err());
+}
+
+#[test]
+fn t_options_cli() {
+    Assert::main_binary()
+        .with_args(&["-ne", "a\\tb"])
+        .stdout().is("a\tb")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-eE", "a\\tb"])
+        .stdout().is("a\\tb")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-nope", "-", "-n"])
+        .stdout().is("-nope - -n")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-e", "one\\ctwo", "three"])
+        .stdout().is("one")
+        .unwrap();
+}
+
+#[test]
+fn t_posix_cli() {
+    Assert::main_binary()
+        .with_env(Environment::inherit().insert("POSIXLY_CORRECT", "1"))
+        .with_args(&["-e", "a\\tb"])
+        .stdout().is("-e a\tb")
+        .unwrap();
+    Assert::main_binary()
+        .with_env(Environment::inherit().insert("POSIXLY_CORRECT", "1"))
+        .with_args(&["-n", "-E", "a\\tb"])
+        .stdout().is("a\tb")
+        .unwrap();
+    Assert::main_binary()
+        .with_env(Environment::inherit().insert("POSIXLY_CORRECT", "1"))
+        .with_args(&["--help"])
+        .stdout().is("--help")
+        .unwrap();
+}
+
+#[test]
+fn t_help_cli() {
+    Assert::main_binary()
+        .with_args(&["--help"])
+        .stdout().contains("USAGE:")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["--help", "me"])
+        .stdout().is("--help me")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-h"])
+        .stdout().is("-h")
+        .unwrap();
+}
@@ -0,0 +1,389 @@
+/*
+ * grep/main.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+extern crate grep;
+extern crate clap;
+extern crate glob;
+extern crate libc;
+
+use clap::{Arg, App, ErrorKind};
+use glob::Pattern;
+use grep::*;
+use std::env;
+use std::ffi::OsStr;
+use std::io::{stderr, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::process;
+
+fn main() {
+    let matches = App::new("grep").about("Search for PATTERN in each FILE, or standard input\nPATTERN is a basic regular expression by default")
+        .arg(Arg::with_name("PATTERN")
+             .help("The pattern to search for, unless -e or -f is given")
+             .required_unless_one(&["regexp", "file"])
+             .index(1))
+        .arg(Arg::with_name("FILE")
+             .help("The files to search, standard input by default or if FILE is -")
+             .required(false)
+             .index(2)
+             .multiple(true))
+        .arg(Arg::with_name("regexp")
+             .short("e")
+             .long("regexp")
+             .help("Use PATTERN for matching, can be given more than once")
+             .value_name("PATTERN")
+             .multiple(true)
+             .number_of_values(1)
+             .allow_hyphen_values(true)
+             .takes_value(true))
+        .arg(Arg::with_name("file")
+             .short("f")
+             .long("file")
+             .help("Take patterns from FILE, one per line")
+             .value_name("FILE")
+             .multiple(true)
+             .number_of_values(1)
+             .takes_value(true))
+        .arg(Arg::with_name("extended-regexp")
+             .short("E")
+             .long("extended-regexp")
+             .help("PATTERN is an extended regular expression")
+             .takes_value(false))
+        .arg(Arg::with_name("fixed-strings")
+             .short("F")
+             .long("fixed-strings")
+             .help("PATTERN is a string, matched literally")
+             .takes_value(false))
+        .arg(Arg::with_name("basic-regexp")
+             .short("G")
+             .long("basic-regexp")
+             .help("PATTERN is a basic regular expression (default)")
+             .takes_value(false))
+        .arg(Arg::with_name("ignore-case")
+             .short("i")
+             .long("ignore-case")
+             .help("Ignore case distinctions in patterns and data")
+             .takes_value(false))
+        .arg(Arg::with_name("word-regexp")
+             .short("w")
+             .long("word-regexp")
+             .help("Select only lines where PATTERN matches whole words")
+             .takes_value(false))
+        .arg(Arg::with_name("line-regexp")
+             .short("x")
+             .long("line-regexp")
+             .help("Select only lines where PATTERN matches the whole line")
+             .takes_value(false))
+        .arg(Arg::with_name("invert-match")
+             .short("v")
+             .long("invert-match")
+             .help("Select non-matching lines")
+             .takes_value(false))
+        .arg(Arg::with_name("count")
+             .short("c")
+             .long("count")
+             .help("Print only a count of selected lines per FILE")
+             .takes_value(false))
+        .arg(Arg::with_name("files-with-matches")
+             .short("l")
+             .long("files-with-matches")
+             .help("Print only names of FILEs with selected lines")
+             .takes_value(false))
+        .arg(Arg::with_name("files-without-match")
+             .short("L")
+             .long("files-without-match")
+             .help("Print only names of FILEs with no selected lines")
+             .takes_value(false))
+        .arg(Arg::with_name("line-number")
+             .short("n")
+             .long("line-number")
+             .help("Print line number with output lines")
+             .takes_value(false))
+        .arg(Arg::with_name("byte-offset")
+             .short("b")
+             .long("byte-offset")
+             .help("Print the byte offset with output lines")
+             .takes_value(false))
+        .arg(Arg::with_name("with-filename")
+             .short("H")
+             .long("with-filename")
+             .help("Print file name with output lines")
+             .overrides_with("no-filename")
+             .takes_value(false))
+        .arg(Arg::with_name("no-filename")
+             .short("h")
+             .long("no-filename")
+             .help("Suppress the file name prefix on output")
+             .overrides_with("with-filename")
+             .takes_value(false))
+        .arg(Arg::with_name("label")
+             .long("label")
+             .help("Use LABEL as the file name for standard input")
+             .value_name("LABEL")
+             .takes_value(true))
+        .arg(Arg::with_name("only-matching")
+             .short("o")
+             .long("only-matching")
+             .help("Show only the part of a line matching PATTERN")
+             .takes_value(false))
+        .arg(Arg::with_name("quiet")
+             .short("q")
+             .long("quiet")
+             .alias("silent")
+             .help("Suppress all normal output, exit as soon as a line is selected")
+             .takes_value(false))
+        .arg(Arg::with_name("no-messages")
+             .short("s")
+             .long("no-messages")
+             .help("Suppress error messages")
+             .takes_value(false))
+        .arg(Arg::with_name("max-count")
+             .short("m")
+             .long("max-count")
+             .help("Stop after NUM selected lines")
+             .value_name("NUM")
+             .takes_value(true))
+        .arg(Arg::with_name("recursive")
+             .short("r")
+             .long("recursive")
+             .help("Search directories recursively, following symbolic links only on the command line")
+             .takes_value(false))
+        .arg(Arg::with_name("dereference-recursive")
+             .short("R")
+             .long("dereference-recursive")
+             .help("Search directories recursively, following all symbolic links")
+             .takes_value(false))
+        .arg(Arg::with_name("include")
+             .long("include")
+             .help("Search only files whose base name matches GLOB")
+             .value_name("GLOB")
+             .multiple(true)
+             .number_of_values(1)
+             .takes_value(true))
+        .arg(Arg::with_name("exclude")
+             .long("exclude")
+             .help("Skip files whose base name matches GLOB")
+             .value_name("GLOB")
+             .multiple(true)
+             .number_of_values(1)
+             .takes_value(true))
+        .arg(Arg::with_name("exclude-dir")
+             .long("exclude-dir")
+             .help("Skip directories whose base name matches GLOB when recursing")
+             .value_name("GLOB")
+             .multiple(true)
+             .number_of_values(1)
+             .takes_value(true))
+        .arg(Arg::with_name("binary-files")
+             .long("binary-files")
+             .help("Assume binary files are TYPE: 'binary', 'text', or 'without-match'")
+             .value_name("TYPE")
+             .possible_values(&["binary", "text", "without-match"])
+             .takes_value(true))
+        .arg(Arg::with_name("text")
+             .short("a")
+             .long("text")
+             .help("Process a binary file as if it were text, same as --binary-files=text")
+             .takes_value(false))
+        .arg(Arg::with_name("ignore-binary")
+             .short("I")
+             .help("Assume binary files do not match, same as --binary-files=without-match")
+             .takes_value(false))
+        .arg(Arg::with_name("after-context")
+             .short("A")
+             .long("after-context")
+             .help("Print NUM lines of trailing context")
+             .value_name("NUM")
+             .takes_value(true))
+        .arg(Arg::with_name("before-context")
+             .short("B")
+             .long("before-context")
+             .help("Print NUM lines of leading context")
+             .value_name("NUM")
+             .takes_value(true))
+        .arg(Arg::with_name("context")
+             .short("C")
+             .long("context")
+             .help("Print NUM lines of output context")
+             .value_name("NUM")
+             .takes_value(true))
+        .arg(Arg::with_name("group-separator")
+             .long("group-separator")
+             .help("Print SEP between groups of context lines, instead of --")
+             .value_name("SEP")
+             .takes_value(true))
+        .arg(Arg::with_name("no-group-separator")
+             .long("no-group-separator")
+             .help("Print nothing between groups of context lines")
+             .takes_value(false))
+        .arg(Arg::with_name("null-data")
+             .short("z")
+             .long("null-data")
+             .help("Lines of input and output end with a NUL byte, not a newline")
+             .takes_value(false))
+        .arg(Arg::with_name("threads")
+             .long("threads")
+             .help("Search NUM files at once, one per CPU by default")
+             .value_name("NUM")
+             .takes_value(true))
+        .arg(Arg::with_name("color")
+             .long("color")
+             .alias("colour")
+             .help("Highlight matches with the colors in GREP_COLORS: 'always', 'never', or 'auto'")
+             .value_name("WHEN")
+             .possible_values(&["always", "never", "auto"])
+             .min_values(0)
+             .require_equals(true)
+             .takes_value(true))
+        .get_matches_safe()
+        .unwrap_or_else(|e| match e.kind {
+            ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => e.exit(),
+            // trouble with the arguments is trouble like any other
+            _ => {
+                let _ = writeln!(stderr(), "{}", e.message);
+                process::exit(EXIT_TROUBLE);
+            }
+        });
+
+    let syntax = if matches.is_present("fixed-strings") {
+        Syntax::Fixed
+    } else if matches.is_present("extended-regexp") {
+        Syntax::Extended
+    } else {
+        Syntax::Basic
+    };
+
+    let number = |name: &str, what: &str| -> Option<u64> {
+        matches.value_of_lossy(name).map(|n| match n.parse::<u64>() {
+            Ok(n) => n,
+            Err(_e) => {
+                let _ = writeln!(stderr(), "grep: {}: invalid {} argument", n, what);
+                process::exit(EXIT_TROUBLE);
+            }
+        })
+    };
+
+    let max_count = number("max-count", "max count");
+    let context = number("context", "context length").unwrap_or(0);
+    let before_context = number("before-context", "context length").unwrap_or(context);
+    let after_context = number("after-context", "context length").unwrap_or(context);
+
+    let group_separator = if matches.is_present("no-group-separator") {
+        None
+    } else {
+        Some(matches.value_of_lossy("group-separator").map_or_else(|| String::from("--"), |s| s.into_owned()))
+    };
+
+    let binary_files = if matches.is_present("text") {
+        BinaryFiles::Text
+    } else if matches.is_present("ignore-binary") {
+        BinaryFiles::WithoutMatch
+    } else {
+        match matches.value_of("binary-files") {
+            Some("text") => BinaryFiles::Text,
+            Some("without-match") => BinaryFiles::WithoutMatch,
+            _ => BinaryFiles::Binary
+        }
+    };
+
+    let globs = |name: &str| -> Vec<Pattern> {
+        matches.values_of_lossy(name).map(|v| v.iter().map(|g| match Pattern::new(g) {
+            Ok(p) => p,
+            Err(e) => {
+                let _ = writeln!(stderr(), "grep: {}: {}", g, e);
+                process::exit(EXIT_TROUBLE);
+            }
+        }).collect()).unwrap_or_default()
+    };
+
+    // auto (or no WHEN at all) means colors for a terminal that can show them
+    let color = match matches.value_of("color") {
+        Some("always") => true,
+        Some("never") => false,
+        _ if matches.is_present("color") => {
+            let tty = unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 };
+            tty && env::var("TERM").map(|t| t != "dumb").unwrap_or(false)
+        },
+        _ => false
+    };
+
+    // with -e or -f, the first operand is a file rather than the pattern
+    let mut patterns: Vec<Vec<u8>> = Vec::new();
+    let mut files: Vec<&OsStr> = Vec::new();
+    if matches.is_present("regexp") || matches.is_present("file") {
+        patterns.extend(matches.values_of_os("regexp").into_iter().flatten().map(|p| p.as_bytes().to_vec()));
+        for file in matches.values_of_os("file").into_iter().flatten() {
+            match read_patterns(file) {
+                Ok(p) => patterns.extend(p),
+                Err(e) => {
+                    let _ = writeln!(stderr(), "{}", e);
+                    process::exit(EXIT_TROUBLE);
+                }
+            }
+        }
+        files.extend(matches.value_of_os("PATTERN"));
+    } else {
+        patterns.extend(matches.value_of_os("PATTERN").map(|p| p.as_bytes().to_vec()));
+    }
+    files.extend(matches.values_of_os("FILE").into_iter().flatten());
+
+    let dereference_recursive = matches.is_present("dereference-recursive");
+    let recursive = dereference_recursive || matches.is_present("recursive");
+
+    // name the files in the output if there could be more than one of them
+    let with_filename = if matches.is_present("with-filename") {
+        true
+    } else if matches.is_present("no-filename") {
+        false
+    } else if recursive {
+        files.len() != 1 || Path::new(files[0]).is_dir()
+    } else {
+        files.len() > 1
+    };
+
+    let options = Options {
+        syntax,
+        ignore_case: matches.is_present("ignore-case"),
+        invert: matches.is_present("invert-match"),
+        count: matches.is_present("count"),
+        files_with_matches: matches.is_present("files-with-matches"),
+        files_without_match: matches.is_present("files-without-match"),
+        line_number: matches.is_present("line-number"),
+        with_filename,
+        only_matching: matches.is_present("only-matching"),
+        quiet: matches.is_present("quiet"),
+        no_messages: matches.is_present("no-messages"),
+        max_count,
+        recursive,
+        dereference_recursive,
+        include: globs("include"),
+        exclude: globs("exclude"),
+        exclude_dir: globs("exclude-dir"),
+        binary_files,
+        before_context,
+        after_context,
+        context: matches.is_present("context") || matches.is_present("before-context") || matches.is_present("after-context"),
+        group_separator,
+        null_data: matches.is_present("null-data"),
+        word_regexp: matches.is_present("word-regexp"),
+        line_regexp: matches.is_present("line-regexp"),
+        byte_offset: matches.is_present("byte-offset"),
+        label: matches.value_of_lossy("label").map(|l| l.into_owned()),
+        color,
+        threads: number("threads", "thread count").unwrap_or(0) as usize
+    };
+
+    let regex = match compile(&patterns, &options) {
+        Ok(r) => r,
+        Err(e) => {
+            let _ = writeln!(stderr(), "{}", e);
+            process::exit(EXIT_TROUBLE);
+        }
+    };
+
+    process::exit(search(&files, &regex, &options));
+}
@@ -0,0 +1,115 @@
+/*
+ * grep/color.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+//!
+//! Colors for grep output, taken from the GREP_COLORS environment variable.
+//!
+//! GREP_COLORS is a colon-separated list of capabilities, like LS_COLORS. Most give the SGR
+//! sequence used for one part of the output:
+//!
+//! - `ms`: matched text in selected lines, and `mc`: matched text in context lines
+//! - `mt`: sets both `ms` and `mc`
+//! - `sl`: whole selected lines, and `cx`: whole context lines
+//! - `fn`: file names, `ln`: line numbers, `bn`: byte offsets, and `se`: separators
+//!
+//! The boolean `rv` swaps `sl` and `cx` when -v is given, and `ne` leaves out the "erase in
+//! line" sequence that normally follows each color.
+//!
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, Write};
+
+static DEFAULT_COLORS: &str = "ms=01;31:mc=01;31:sl=:cx=:fn=35:ln=32:bn=32:se=36";
+
+lazy_static! {
+    static ref GREP_COLORS: String = env::var("GREP_COLORS").unwrap_or_default();
+    static ref COLOR_MAP: HashMap<&'static str, &'static str> = parse_colors(&GREP_COLORS);
+}
+
+/// Reads a GREP_COLORS string on top of the default colors. Boolean capabilities are given an
+/// empty value, and entries that don't make sense are ignored.
+pub fn parse_colors(spec: &str) -> HashMap<&str, &str> {
+    let mut map = HashMap::new();
+    for c in DEFAULT_COLORS.split(':').chain(spec.split(':')) {
+        let p: Vec<_> = c.splitn(2, '=').collect();
+        match (p[0], p.get(1)) {
+            ("rv", None) | ("ne", None) => {
+                map.insert(p[0], "");
+            },
+            ("mt", Some(code)) => {
+                map.insert("ms", *code);
+                map.insert("mc", *code);
+            },
+            (cap, Some(code)) if cap.len() == 2 && code.bytes().all(|b| b.is_ascii_digit() || b == b';') => {
+                map.insert(cap, *code);
+            },
+            _ => {}
+        }
+    }
+    map
+}
+
+/// The colors to use while printing.
+#[derive(Debug)]
+pub struct Palette<'a> {
+    map: &'a HashMap<&'a str, &'a str>,
+}
+
+impl Palette<'static> {
+    /// The colors from the environment.
+    pub fn from_env() -> Palette<'static> {
+        Palette { map: &COLOR_MAP }
+    }
+}
+
+impl<'a> Palette<'a> {
+    pub fn new(map: &'a HashMap<&'a str, &'a str>) -> Palette<'a> {
+        Palette { map }
+    }
+
+    /// The SGR sequence for *cap*, which is empty if it is turned off.
+    pub fn code(&self, cap: &str) -> &'a str {
+        self.map.get(cap).cloned().unwrap_or("")
+    }
+
+    /// Is the boolean capability *cap* turned on?
+    pub fn flag(&self, cap: &str) -> bool {
+        self.map.contains_key(cap)
+    }
+
+    /// Switches to the color for *cap*, if it has one.
+    pub fn start<W: Write>(&self, writer: &mut W, cap: &str) -> io::Result<()> {
+        let code = self.code(cap);
+        if code.is_empty() {
+            return Ok(());
+        }
+        write!(writer, "\x1b[{}m", code)?;
+        if !self.flag("ne") {
+            writer.write_all(b"\x1b[K")?;
+        }
+        Ok(())
+    }
+
+    /// Switches back to the default color, if *cap* had changed it.
+    pub fn end<W: Write>(&self, writer: &mut W, cap: &str) -> io::Result<()> {
+        if self.code(cap).is_empty() {
+            return Ok(());
+        }
+        writer.write_all(b"\x1b[m")?;
+        if !self.flag("ne") {
+            writer.write_all(b"\x1b[K")?;
+        }
+        Ok(())
+    }
+
+    /// Writes *text* in the color for *cap*.
+    pub fn paint<W: Write>(&self, writer: &mut W, cap: &str, text: &[u8]) -> io::Result<()> {
+        self.start(writer, cap)?;
+        writer.write_all(text)?;
+        self.end(writer, cap)
+    }
+}
@@ -0,0 +1,862 @@
+/*
+ * grep/lib.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+//!
+//! # **`grep`**
+//!
+//! Print lines of FILEs (or standard input) that match a PATTERN.
+//!
+//! ```rust
+//! extern crate grep;
+//! ```
+//!
+//! Patterns are basic regular expressions by default. Use -E for extended regular expressions,
+//! or -F to match fixed strings. More patterns can be given with -e, or read from a file, one per
+//! line, with -f. A line is selected if any of them match.
+//!
+//! ```text
+//! $ grep -E 'fn (main|grep)' src/*.rs
+//! $ grep -c '\(ab\)\1' file.txt
+//! $ grep -F -f deny-list.txt -e localhost hosts.txt
+//! ```
+//!
+//! The exit status is 0 if a line is selected, 1 if no lines were selected, and 2 if an error
+//! occurred. If -q is given and a line is selected, the exit status is 0 even if an error occurred.
+//!
+#[macro_use]
+extern crate quick_error;
+#[macro_use]
+extern crate lazy_static;
+extern crate aho_corasick;
+extern crate memchr;
+extern crate glob;
+extern crate libc;
+extern crate walkdir;
+
+use glob::Pattern;
+use quick_error::ResultExt;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{self, stderr, stdin, stdout, BufRead, BufReader, BufWriter, ErrorKind, Read, Stdout, Write};
+use std::sync::{mpsc, Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+
+pub mod color;
+pub mod regex;
+mod walk;
+
+pub use crate::color::Palette;
+pub use crate::regex::{Config, Regex, Syntax};
+pub use crate::walk::Input;
+
+/// Exit status when a line was selected.
+pub const EXIT_MATCH: i32 = 0;
+/// Exit status when no lines were selected.
+pub const EXIT_NO_MATCH: i32 = 1;
+/// Exit status when something went wrong.
+pub const EXIT_TROUBLE: i32 = 2;
+
+quick_error! {
+    /// Problems grep can run into
+    #[derive(Debug)]
+    pub enum Errors {
+        Input(err: io::Error, path: String) {
+            display("grep: {0}: {1}", path, err)
+            context(path: &'a str, err: io::Error) -> (err, path.to_owned())
+            cause(err)
+        }
+
+        Output(err: io::Error) {
+            display("grep: {0}", err) from()
+            cause(err)
+        }
+
+        Pattern(err: regex::Error) {
+            display("grep: {0}", err) from()
+            cause(err)
+        }
+
+        Directory(p: String) {
+            display("grep: {0}: Is a directory", p)
+        }
+    }
+}
+
+/// What to do with files that look like binary data.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryFiles {
+    /// Print a one-line message instead of the matching lines
+    Binary,
+    /// Search the file as if it were text: -a or --text
+    Text,
+    /// Assume the file does not match: -I
+    WithoutMatch,
+}
+
+/// All of the options for grep. These are registered from clap arguments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    /// How to interpret the pattern: -E, -F or -G
+    pub syntax: Syntax,
+
+    /// Ignore case distinctions in patterns and input data: -i or --ignore-case
+    pub ignore_case: bool,
+
+    /// Select non-matching lines: -v or --invert-match
+    pub invert: bool,
+
+    /// Only print a count of selected lines per file: -c or --count
+    pub count: bool,
+
+    /// Only print the names of files with selected lines: -l or --files-with-matches
+    pub files_with_matches: bool,
+
+    /// Only print the names of files with no selected lines: -L or --files-without-match
+    pub files_without_match: bool,
+
+    /// Prefix each line of output with its line number: -n or --line-number
+    pub line_number: bool,
+
+    /// Prefix each line of output with the file name: -H, or turned off with -h
+    pub with_filename: bool,
+
+    /// Print only the matched parts of a line, each on its own line: -o or --only-matching
+    pub only_matching: bool,
+
+    /// Print nothing, and exit as soon as a line is selected: -q or --quiet
+    pub quiet: bool,
+
+    /// Suppress error messages about missing or unreadable files: -s or --no-messages
+    pub no_messages: bool,
+
+    /// Stop reading a file after this many selected lines: -m or --max-count
+    pub max_count: Option<u64>,
+
+    /// Search directories recursively: -r or --recursive
+    pub recursive: bool,
+
+    /// Follow every symbolic link while searching recursively: -R or --dereference-recursive
+    pub dereference_recursive: bool,
+
+    /// Only search files whose base name matches one of these: --include
+    pub include: Vec<Pattern>,
+
+    /// Skip files whose base name matches one of these: --exclude
+    pub exclude: Vec<Pattern>,
+
+    /// Skip directories whose base name matches one of these while recursing: --exclude-dir
+    pub exclude_dir: Vec<Pattern>,
+
+    /// How to treat binary files: --binary-files, -a or -I
+    pub binary_files: BinaryFiles,
+
+    /// Lines of context to print before each selected line: -B, --before-context, or -C
+    pub before_context: u64,
+
+    /// Lines of context to print after each selected line: -A, --after-context, or -C
+    pub after_context: u64,
+
+    /// Whether -A, -B or -C was given at all. Groups are separated then even with no context
+    /// lines, as GNU does for -A 0
+    pub context: bool,
+
+    /// Printed between groups of context lines, "--" by default: --group-separator, or None
+    /// with --no-group-separator
+    pub group_separator: Option<String>,
+
+    /// Lines end with a NUL byte instead of a newline, in both input and output: -z or --null-data
+    pub null_data: bool,
+
+    /// Only select lines where the match is a whole word: -w or --word-regexp
+    pub word_regexp: bool,
+
+    /// Only select lines that match as a whole: -x or --line-regexp
+    pub line_regexp: bool,
+
+    /// Prefix each line of output with its byte offset in the input: -b or --byte-offset
+    pub byte_offset: bool,
+
+    /// The name to show for standard input, instead of "(standard input)": --label
+    pub label: Option<String>,
+
+    /// Highlight matches, file names, line numbers and separators using GREP_COLORS: --color
+    pub color: bool,
+
+    /// How many files to search at once, or 0 for one per CPU: --threads
+    pub threads: usize,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            syntax: Syntax::Basic,
+            ignore_case: false,
+            invert: false,
+            count: false,
+            files_with_matches: false,
+            files_without_match: false,
+            line_number: false,
+            with_filename: false,
+            only_matching: false,
+            quiet: false,
+            no_messages: false,
+            max_count: None,
+            recursive: false,
+            dereference_recursive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            exclude_dir: Vec::new(),
+            binary_files: BinaryFiles::Binary,
+            before_context: 0,
+            after_context: 0,
+            context: false,
+            group_separator: Some(String::from("--")),
+            null_data: false,
+            word_regexp: false,
+            line_regexp: false,
+            byte_offset: false,
+            label: None,
+            color: false,
+            threads: 0,
+        }
+    }
+}
+
+type GrepResult<T> = Result<T, Errors>;
+
+/// Compiles *patterns* into one regex, with the syntax and case settings in *options*. A pattern
+/// with newlines in it is split into one pattern per line. Patterns are bytes, and don't have to
+/// be valid UTF-8.
+pub fn compile<P: AsRef<[u8]>>(patterns: &[P], options: &Options) -> GrepResult<Regex> {
+    let config = Config {
+        syntax: options.syntax,
+        ignore_case: options.ignore_case,
+        word: options.word_regexp,
+        line: options.line_regexp,
+    };
+    let patterns: Vec<&[u8]> = patterns.iter().flat_map(|p| p.as_ref().split(|&b| b == b'\n')).collect();
+    Ok(Regex::with_patterns(&patterns, &config)?)
+}
+
+/// Reads the patterns in *file*, one per line, for -f. "-" is standard input. An empty file
+/// has no patterns at all. The lines are kept as bytes, like patterns given as arguments.
+pub fn read_patterns(file: &OsStr) -> GrepResult<Vec<Vec<u8>>> {
+    let name = file.to_string_lossy();
+    let file = &name[..];
+    let mut bytes = Vec::new();
+    if file == "-" {
+        stdin().read_to_end(&mut bytes).context(file)?;
+    } else {
+        File::open(file).and_then(|mut f| f.read_to_end(&mut bytes)).context(file)?;
+    }
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let text = bytes.strip_suffix(b"\n").unwrap_or(&bytes);
+    Ok(text.split(|&b| b == b'\n').map(|line| line.to_vec()).collect())
+}
+
+/// Output state carried from one file to the next.
+#[derive(Debug, Default)]
+pub struct OutputState {
+    /// Whether any lines have been printed yet. The first group of context lines from the next
+    /// file needs a separator if so.
+    pub printed: bool,
+}
+
+/// Writes the separator between groups of context lines, if there is one.
+fn write_separator<W: Write>(writer: &mut W, options: &Options) -> io::Result<()> {
+    if !options.context && options.before_context == 0 && options.after_context == 0 {
+        return Ok(());
+    }
+    if let Some(group) = options.group_separator.as_ref() {
+        if options.color {
+            Palette::from_env().paint(writer, "se", group.as_bytes())?;
+        } else {
+            writer.write_all(group.as_bytes())?;
+        }
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Writes the lines of one file, with their prefixes, colors, and the separators between
+/// context groups.
+struct Printer<'a, W: Write + 'a> {
+    writer: &'a mut W,
+    regex: &'a Regex,
+    name: &'a str,
+    options: &'a Options,
+    state: &'a mut OutputState,
+    palette: Option<Palette<'static>>,
+    eol: u8,              // the line terminator
+    last: Option<u64>,    // number of the last line printed from this file
+}
+
+impl<'a, W: Write> Printer<'a, W> {
+    /// Writes *text* in the color for *cap*, if colors are on.
+    fn paint(&mut self, cap: &str, text: &[u8]) -> io::Result<()> {
+        match self.palette {
+            Some(ref palette) => palette.paint(self.writer, cap, text),
+            None => self.writer.write_all(text),
+        }
+    }
+
+    /// Starts the output for line *n*, writing a group separator first if it doesn't follow
+    /// straight on from the last line printed. *offset* is where the output starts in the input,
+    /// for -b. *sep* is ':' for selected lines and '-' for context.
+    fn start(&mut self, n: u64, offset: u64, sep: char) -> io::Result<()> {
+        let gap = match self.last {
+            Some(last) => n > last + 1,
+            None => self.state.printed,
+        };
+        if gap {
+            write_separator(self.writer, self.options)?;
+        }
+        self.last = Some(n);
+        self.state.printed = true;
+
+        let sep = [sep as u8];
+        if self.options.with_filename {
+            self.paint("fn", self.name.as_bytes())?;
+            self.paint("se", &sep)?;
+        }
+        if self.options.line_number {
+            self.paint("ln", n.to_string().as_bytes())?;
+            self.paint("se", &sep)?;
+        }
+        if self.options.byte_offset {
+            self.paint("bn", offset.to_string().as_bytes())?;
+            self.paint("se", &sep)?;
+        }
+        Ok(())
+    }
+
+    /// Writes *bytes* as a whole line of output, with the line terminator after it.
+    fn finish(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.writer.write_all(&[self.eol])
+    }
+
+    fn line(&mut self, n: u64, offset: u64, line: &[u8], sep: char) -> io::Result<()> {
+        self.start(n, offset, sep)?;
+        let palette = match self.palette {
+            Some(ref palette) => palette,
+            None => return self.finish(line),
+        };
+
+        // the whole line gets its own color, which is picked up again after each match
+        let selected = sep == ':';
+        let swap = palette.flag("rv") && self.options.invert;
+        let line_cap = if selected != swap { "sl" } else { "cx" };
+        let match_cap = if selected { "ms" } else { "mc" };
+        let mut pos = 0;
+        let mut open = true;
+
+        palette.start(self.writer, line_cap)?;
+        for (s, e) in self.regex.find_iter(line) {
+            if s > pos {
+                if !open {
+                    palette.start(self.writer, line_cap)?;
+                }
+                self.writer.write_all(&line[pos..s])?;
+            }
+            palette.paint(self.writer, match_cap, &line[s..e])?;
+            open = false;
+            pos = e;
+        }
+        if pos < line.len() {
+            if !open {
+                palette.start(self.writer, line_cap)?;
+            }
+            self.writer.write_all(&line[pos..])?;
+            open = true;
+        }
+        if open {
+            palette.end(self.writer, line_cap)?;
+        }
+        self.writer.write_all(&[self.eol])
+    }
+
+    /// Prints each match in *line* on its own, for -o. With -b, each one gets its own offset.
+    fn matches(&mut self, n: u64, offset: u64, line: &[u8]) -> io::Result<()> {
+        for (s, e) in self.regex.find_iter(line) {
+            self.start(n, offset + s as u64, ':')?;
+            self.paint("ms", &line[s..e])?;
+            self.writer.write_all(&[self.eol])?;
+        }
+        Ok(())
+    }
+}
+
+/// Searches each line of *reader* for *regex*, and writes the selected lines to *writer* the
+/// way *options* asks, along with any context lines around them. *name* is the name used for
+/// filename prefixes and for -c, -l and -L output, and *state* is shared by every file written
+/// to the same place. Returns the number of lines selected.
+///
+/// Lines are read as raw bytes, so input doesn't have to be valid UTF-8, and they are printed
+/// exactly as they were read. Input with a NUL byte in it is binary, unless NUL is the line
+/// terminator. Unless *options* says to treat it as text, the lines of a binary file are never
+/// printed: a single "Binary file NAME matches" line is written in their place.
+pub fn grep<R, W>(regex: &Regex, name: &str, mut reader: R, writer: &mut W, options: &Options, state: &mut OutputState) -> GrepResult<u64>
+    where R: BufRead, W: Write
+{
+    let listing = options.quiet || options.files_with_matches || options.files_without_match;
+    let print_lines = !listing && !options.count;
+    let max = options.max_count.unwrap_or(u64::MAX);
+    let mut count: u64 = 0;
+
+    let eol = if options.null_data { b'\0' } else { b'\n' };
+    let detect_binary = options.binary_files != BinaryFiles::Text && !options.null_data;
+    let mut binary = detect_binary && reader.fill_buf().context(name)?.contains(&0);
+    if binary && options.binary_files == BinaryFiles::WithoutMatch {
+        return Ok(0);
+    }
+
+    // context is not shown around the bare matches of -o
+    let (before, after) = if options.only_matching {
+        (0, 0)
+    } else {
+        (options.before_context as usize, options.after_context)
+    };
+    let mut queue: VecDeque<(u64, u64, Vec<u8>)> = VecDeque::with_capacity(before);
+    let mut after_left: u64 = 0;
+    let mut printer = Printer {
+        writer,
+        regex,
+        name,
+        options,
+        state,
+        palette: if options.color { Some(Palette::from_env()) } else { None },
+        eol,
+        last: None,
+    };
+    let mut line: Vec<u8> = Vec::new();
+    let mut n: u64 = 0;
+    let mut next: u64 = 0;    // byte offset of the next line
+
+    // plain strings don't need the input split into lines, unless every line has to be looked at
+    let whole_buffers = regex.is_literal() && !options.invert && !options.context && before == 0 && after == 0 && !options.null_data;
+
+    if max > 0 && whole_buffers {
+        count = search_buffers(&mut printer, &mut reader, binary)?;
+    } else if max > 0 {
+        loop {
+            line.clear();
+            let offset = next;
+            match reader.read_until(eol, &mut line).context(name)? {
+                0 => break,
+                read => next += read as u64,
+            }
+            if line.last() == Some(&eol) {
+                line.pop();
+            }
+            n += 1;
+            if detect_binary && line.contains(&0) {
+                binary = true;
+            }
+
+            // after the last selected line, keep going only as far as its trailing context
+            if count >= max {
+                if after_left == 0 || binary {
+                    break;
+                }
+                printer.line(n, offset, &line, '-')?;
+                after_left -= 1;
+                continue;
+            }
+
+            if regex.is_match(&line) == options.invert {
+                if print_lines && !binary {
+                    if after_left > 0 {
+                        printer.line(n, offset, &line, '-')?;
+                        after_left -= 1;
+                    } else if before > 0 {
+                        if queue.len() == before {
+                            queue.pop_front();
+                        }
+                        queue.push_back((n, offset, line.clone()));
+                    }
+                }
+                continue;
+            }
+            count += 1;
+
+            if print_lines && binary {
+                writeln!(printer.writer, "Binary file {} matches", name)?;
+                break;
+            }
+            if print_lines {
+                for (m, at, context) in queue.drain(..) {
+                    printer.line(m, at, &context, '-')?;
+                }
+                if !options.only_matching {
+                    printer.line(n, offset, &line, ':')?;
+                } else if !options.invert {
+                    printer.matches(n, offset, &line)?;
+                }
+                after_left = after;
+            }
+            // one selected line is enough to know what to print for -q, -l and -L
+            if listing {
+                break;
+            }
+        }
+    }
+
+    if options.quiet {
+        return Ok(count);
+    }
+    if (options.files_with_matches && count > 0) || (options.files_without_match && count == 0) {
+        printer.paint("fn", name.as_bytes())?;
+        printer.writer.write_all(b"\n")?;
+    } else if options.count && !options.files_with_matches && !options.files_without_match {
+        if options.with_filename {
+            printer.paint("fn", name.as_bytes())?;
+            printer.paint("se", b":")?;
+        }
+        writeln!(printer.writer, "{}", count)?;
+    }
+    Ok(count)
+}
+
+/// How much input is searched at a time by `search_buffers`.
+const BUFFER_SIZE: usize = 1 << 20;
+
+/// Searches what is left of *reader* a large buffer at a time, instead of line by line, for the
+/// plain strings in the printer's regex. Only the lines around each match are found, and counted
+/// when line numbers are needed. This is for searches without -v or context lines, where the
+/// lines that don't match are never looked at. *binary* is whether the input is already known
+/// to be binary. Returns the number of lines selected.
+fn search_buffers<R, W>(printer: &mut Printer<W>, reader: &mut R, mut binary: bool) -> GrepResult<u64>
+    where R: Read, W: Write
+{
+    let options = printer.options;
+    let listing = options.quiet || options.files_with_matches || options.files_without_match;
+    let print_lines = !listing && !options.count;
+    let detect_binary = options.binary_files != BinaryFiles::Text;
+    let max = options.max_count.unwrap_or(u64::MAX);
+    let mut count: u64 = 0;
+    let mut n: u64 = 0;
+    let mut searched: u64 = 0;    // byte offset of the start of the buffer
+
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut len = 0;
+    loop {
+        // a line that doesn't fit in the buffer makes it bigger
+        if len == buffer.len() {
+            buffer.resize(len * 2, 0);
+        }
+        let got = match reader.read(&mut buffer[len..]) {
+            Ok(got) => got,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Errors::Input(e, printer.name.to_owned())),
+        };
+        len += got;
+        let eof = got == 0;
+
+        // search up to the end of the last whole line, and keep the rest for next time
+        let end = match memchr::memrchr(b'\n', &buffer[..len]) {
+            _ if eof => len,
+            Some(i) => i + 1,
+            None => continue,
+        };
+        let text = &buffer[..end];
+        let nul = if detect_binary && !binary { memchr::memchr(0, text) } else { None };
+        let mut pos = 0;
+
+        while pos < end {
+            let s = match printer.regex.find_at(text, pos) {
+                Some((s, _)) => s,
+                None => break,
+            };
+            let start = memchr::memrchr(b'\n', &text[pos..s]).map(|i| pos + i + 1).unwrap_or(pos);
+            let stop = memchr::memchr(b'\n', &text[s..]).map(|i| s + i).unwrap_or(end);
+            if options.line_number {
+                n += memchr::memchr_iter(b'\n', &text[pos..start]).count() as u64 + 1;
+            }
+            pos = (stop + 1).min(end);
+            count += 1;
+
+            if nul.is_some_and(|z| z < stop) {
+                binary = true;
+            }
+            if print_lines && binary {
+                writeln!(printer.writer, "Binary file {} matches", printer.name)?;
+                return Ok(count);
+            }
+            if print_lines {
+                let offset = searched + start as u64;
+                if options.only_matching {
+                    printer.matches(n, offset, &text[start..stop])?;
+                } else {
+                    printer.line(n, offset, &text[start..stop], ':')?;
+                }
+            }
+            // one selected line is enough to know what to print for -q, -l and -L
+            if listing || count >= max {
+                return Ok(count);
+            }
+        }
+        if options.line_number {
+            n += memchr::memchr_iter(b'\n', &text[pos..]).count() as u64;
+        }
+        binary |= nul.is_some();
+
+        if eof {
+            return Ok(count);
+        }
+        buffer.copy_within(end..len, 0);
+        len -= end;
+        searched += end as u64;
+    }
+}
+
+/// Opens *input* and searches it.
+fn grep_input<W: Write>(regex: &Regex, input: &Input, writer: &mut W, options: &Options, state: &mut OutputState) -> GrepResult<u64> {
+    if input.is_stdin() {
+        let stdin = stdin();
+        let reader = stdin.lock();
+        grep(regex, &input.name, reader, writer, options, state)
+    } else {
+        let f = File::open(&input.path).context(&input.name[..])?;
+        grep(regex, &input.name, BufReader::new(f), writer, options, state)
+    }
+}
+
+/// What the search has come across so far, for the exit status.
+#[derive(Debug, Default)]
+struct Status {
+    selected: bool,
+    trouble: bool,
+    broken: Option<io::Error>,
+}
+
+impl Status {
+    /// Takes note of the *result* of searching one input, reporting any error. Returns false if
+    /// the search should stop here.
+    fn record<W: Write>(&mut self, result: GrepResult<u64>, writer: &mut W, options: &Options) -> bool {
+        match result {
+            Ok(count) => {
+                // success means a line was selected, even with -L, as in GNU grep 3.5 and later
+                if count > 0 {
+                    self.selected = true;
+                    if options.quiet {
+                        return false;
+                    }
+                }
+            },
+            Err(Errors::Output(e)) => {
+                self.broken = Some(e);
+                return false;
+            },
+            Err(e) => {
+                if !options.no_messages {
+                    let _ = writer.flush();
+                    let _ = writeln!(stderr(), "{}", e);
+                }
+                self.trouble = true;
+            }
+        }
+        true
+    }
+
+    /// Flushes the output, and works out the exit status.
+    fn finish<W: Write>(mut self, writer: &mut W, options: &Options) -> i32 {
+        if self.broken.is_none() {
+            self.broken = writer.flush().err();
+        }
+        if let Some(e) = self.broken {
+            if e.kind() != ErrorKind::BrokenPipe {
+                let _ = writeln!(stderr(), "{}", Errors::Output(e));
+            }
+            return EXIT_TROUBLE;
+        }
+
+        if self.trouble && !(options.quiet && self.selected) {
+            EXIT_TROUBLE
+        } else if self.selected {
+            EXIT_MATCH
+        } else {
+            EXIT_NO_MATCH
+        }
+    }
+}
+
+/// Most output a worker holds on to for one input. An input with more is searched again when
+/// its turn comes, straight to stdout.
+const HELD_OUTPUT: usize = 256 * 1024;
+
+/// What a worker made of one input.
+enum Found {
+    /// The result of searching it, with its output waiting its turn to be printed.
+    Done { result: GrepResult<u64>, output: Vec<u8>, printed: bool },
+    /// Left to be searched in its turn: standard input, or an input with too much output.
+    Later(Input),
+}
+
+/// A worker's output buffer, which fails any write past HELD_OUTPUT.
+struct Held(Vec<u8>);
+
+impl Write for Held {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.0.len() + buf.len() > HELD_OUTPUT {
+            return Err(io::Error::other("too much output to hold"));
+        }
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Locks *stdout* for writing. Output to a terminal goes out a line at a time, as usual, but
+/// anywhere else it is written in large blocks.
+fn output(stdout: &Stdout) -> Box<dyn Write + '_> {
+    if unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1 {
+        Box::new(stdout.lock())
+    } else {
+        Box::new(BufWriter::with_capacity(64 * 1024, stdout.lock()))
+    }
+}
+
+/// Searches every file in *files* for *regex*, printing results to stdout and problems to
+/// stderr. Directories are searched when *options* asks for a recursive search. An empty list
+/// of files means standard input, or the current directory when searching recursively. Returns
+/// the exit status: EXIT_MATCH, EXIT_NO_MATCH, or EXIT_TROUBLE.
+///
+/// Several files are searched at once, on one thread per CPU unless *options* says otherwise,
+/// but the output still comes out in the same order as if they were searched one after another.
+pub fn search(files: &[&OsStr], regex: &Regex, options: &Options) -> i32 {
+    let workers = match options.threads {
+        0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n,
+    };
+    if workers < 2 || (files.len() < 2 && !options.recursive) {
+        search_serial(files, regex, options)
+    } else {
+        search_parallel(files, regex, options, workers)
+    }
+}
+
+/// Searches each input in turn, writing straight to stdout.
+fn search_serial(files: &[&OsStr], regex: &Regex, options: &Options) -> i32 {
+    let stdout = stdout();
+    let mut writer = output(&stdout);
+    let mut status: Status = Default::default();
+    let mut state: OutputState = Default::default();
+
+    walk::expand(files, options, |input| {
+        let result = input.and_then(|input| grep_input(regex, &input, &mut writer, options, &mut state));
+        status.record(result, &mut writer, options)
+    });
+    status.finish(&mut writer, options)
+}
+
+/// Hands the inputs out to *workers* threads, which search them into buffers, and prints the
+/// buffers in order as they come back. Standard input, and any input with more output than a
+/// worker will hold, is searched again in its turn, straight to stdout. The workers only run a
+/// few inputs ahead of the output, so that no more than a few buffers are waiting at once. The
+/// threads aren't waited for, so that a worker stuck reading a slow input can't hold up the exit
+/// once -q has its answer.
+fn search_parallel(files: &[&OsStr], regex: &Regex, options: &Options, workers: usize) -> i32 {
+    let files: Vec<OsString> = files.iter().map(|&f| f.to_owned()).collect();
+    let regex = Arc::new(regex.clone());
+    let options = Arc::new(options.clone());
+    let ahead = workers * 4;
+
+    // a few inputs are queued up ahead of the workers, and the walk waits for them to catch up
+    let (input_tx, input_rx) = mpsc::sync_channel::<(usize, GrepResult<Input>)>(ahead);
+    let input_rx = Arc::new(Mutex::new(input_rx));
+    let (found_tx, found_rx) = mpsc::channel::<(usize, Found)>();
+    // the index of the next input to be printed, which the workers keep within *ahead* of
+    let turn = Arc::new((Mutex::new(0), Condvar::new()));
+
+    for _ in 0..workers {
+        let (regex, options, turn) = (regex.clone(), options.clone(), turn.clone());
+        let (input_rx, found_tx) = (input_rx.clone(), found_tx.clone());
+        thread::spawn(move || loop {
+            let next = input_rx.lock().map(|rx| rx.recv());
+            let (index, input) = match next {
+                Ok(Ok(job)) => job,
+                _ => break,
+            };
+            let (ref next, ref moved) = *turn;
+            let mut next = next.lock().unwrap_or_else(PoisonError::into_inner);
+            while index >= *next + ahead {
+                next = moved.wait(next).unwrap_or_else(PoisonError::into_inner);
+            }
+            drop(next);
+
+            let found = match input {
+                Ok(input) if input.is_stdin() => Found::Later(input),
+                Ok(input) => {
+                    let mut output = Held(Vec::new());
+                    let mut state: OutputState = Default::default();
+                    match grep_input(&regex, &input, &mut output, &options, &mut state) {
+                        Err(Errors::Output(_)) => Found::Later(input),
+                        result => Found::Done { result, output: output.0, printed: state.printed },
+                    }
+                },
+                Err(e) => Found::Done { result: Err(e), output: Vec::new(), printed: false },
+            };
+            if found_tx.send((index, found)).is_err() {
+                break;
+            }
+        });
+    }
+    drop(found_tx);
+
+    let walk_options = options.clone();
+    thread::spawn(move || {
+        let files: Vec<&OsStr> = files.iter().map(|f| &f[..]).collect();
+        let mut index = 0;
+        walk::expand(&files, &walk_options, |input| {
+            index += 1;
+            input_tx.send((index - 1, input)).is_ok()
+        });
+    });
+
+    let stdout = stdout();
+    let mut writer = output(&stdout);
+    let mut status: Status = Default::default();
+    let mut state: OutputState = Default::default();
+    let mut waiting: HashMap<usize, Found> = HashMap::new();
+    let mut next = 0;
+
+    'results: for (index, found) in found_rx {
+        waiting.insert(index, found);
+        while let Some(found) = waiting.remove(&next) {
+            next += 1;
+            let result = match found {
+                Found::Done { result, output, printed } => {
+                    // the first group of context lines from each file is set apart from the last file's
+                    let mut written = Ok(());
+                    if printed && state.printed {
+                        written = write_separator(&mut writer, &options);
+                    }
+                    state.printed |= printed;
+                    written.and_then(|_| writer.write_all(&output)).map_err(Errors::Output).and(result)
+                },
+                Found::Later(input) => grep_input(&regex, &input, &mut writer, &options, &mut state),
+            };
+            let (ref lock, ref moved) = *turn;
+            *lock.lock().unwrap_or_else(PoisonError::into_inner) = next;
+            moved.notify_all();
+            if !status.record(result, &mut writer, &options) {
+                break 'results;
+            }
+        }
+    }
+    status.finish(&mut writer, &options)
+}
+
+#[cfg(test)]
+mod tests;
@@ -1,46 +0,0 @@
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use std::path::PathBuf;
-
-fn grep<R>(target: &str, reader: R) -> io::Result<()>
-    where R: BufRead
-{
-    for res in reader.lines() {
-        let line = res.unwrap();
-        if line.contains(target) {
-            println!("{}", line);
-        }
-    }
-    Ok(())
-}
-
-fn grep_main() -> Result<(), Box<Error>> {
-    let mut args = std::env::args().skip(1);
-    let target = match args.next() {
-        Some(s) => s,
-        None => Err("Usage: grep PATTERN FILE...")?
-    };
-    let files: Vec<PathBuf> = args.map(PathBuf::from).collect();
-
-    if files.is_empty() {
-        let stdin = io::stdin();
-        grep(&target, stdin.lock()).unwrap();
-    } else {
-        for file in files {
-            let f = File::open(file).unwrap();
-            grep(&target, BufReader::new(f)).unwrap();
-        }
-    }
-
-    Ok(())
-}
-
-fn main() {
-    let result = grep_main();
-    if let Err(err) = result {
-        let _ = writeln!(io::stderr(), "{}", err);
-    }
-}
-
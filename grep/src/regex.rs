@@ -0,0 +1,1633 @@
+/*
+ * grep/regex.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+//!
+//! A small POSIX regular expression engine for grep.
+//!
+//! Patterns are parsed as basic (BRE), extended (ERE) or fixed-string syntax into a syntax tree,
+//! then compiled into a program for a little virtual machine. Patterns without back-references
+//! run on a Pike VM, which is linear in the length of the line; patterns with back-references
+//! fall back to a backtracking matcher. The Pike VM runs first on those too, with each
+//! back-reference standing in for a copy of its group, to rule out lines that can't match.
+//! Both report the leftmost-longest match, like POSIX asks.
+//!
+//! Matching works on bytes. Valid UTF-8 sequences are decoded into characters, and any byte that
+//! is not part of one is treated as a single unit. Such a byte is matched by `.` and by negated
+//! bracket expressions, so text in other encodings like Latin-1 can still be searched. Patterns
+//! are bytes too, and a byte in one that isn't part of a character matches that same byte.
+//!
+//! Several patterns can be compiled together, and a line matches if any of them does. When they
+//! are all plain strings, with -F or just no special characters, they skip the virtual machine.
+//! One string is found with memchr's two-way substring search, and more than one with an
+//! Aho-Corasick automaton, which stays fast with thousands of them. When only some of them are
+//! strings, or none are, the automaton looks for a string each pattern needs instead, and a line
+//! only goes through the patterns whose strings are in it.
+//!
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+use memchr::memmem::Finder;
+use std::collections::{HashMap, HashSet};
+use std::{error, fmt, str};
+
+/// The largest count allowed inside an interval expression, like `a\{1,32767\}`.
+const DUP_MAX: u32 = 0x7fff;
+
+/// The largest program a pattern may compile to.
+const MAX_INSTS: usize = 1 << 20;
+
+/// Which flavor of pattern to compile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Syntax {
+    /// POSIX basic regular expressions: -G or --basic-regexp
+    Basic,
+    /// POSIX extended regular expressions: -E or --extended-regexp
+    Extended,
+    /// Fixed strings, matched literally: -F or --fixed-strings
+    Fixed,
+}
+
+/// Settings that change how a pattern is compiled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    /// Which flavor of pattern this is
+    pub syntax: Syntax,
+    /// Ignore case distinctions in the pattern and the text
+    pub ignore_case: bool,
+    /// Only match whole words, with no word characters on either side
+    pub word: bool,
+    /// Only match the whole of the text
+    pub line: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            syntax: Syntax::Basic,
+            ignore_case: false,
+            word: false,
+            line: false,
+        }
+    }
+}
+
+/// A pattern that could not be compiled.
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    msg: String,
+}
+
+impl Error {
+    fn new(msg: &str) -> Error {
+        Error { msg: msg.to_owned() }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl error::Error for Error {}
+
+type ParseResult<T> = Result<T, Error>;
+
+/// Named character classes, as in `[[:alpha:]]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Named {
+    Alnum,
+    Alpha,
+    Blank,
+    Cntrl,
+    Digit,
+    Graph,
+    Lower,
+    Print,
+    Punct,
+    Space,
+    Upper,
+    Xdigit,
+}
+
+impl Named {
+    fn from_name(name: &str) -> Option<Named> {
+        Some(match name {
+            "alnum" => Named::Alnum,
+            "alpha" => Named::Alpha,
+            "blank" => Named::Blank,
+            "cntrl" => Named::Cntrl,
+            "digit" => Named::Digit,
+            "graph" => Named::Graph,
+            "lower" => Named::Lower,
+            "print" => Named::Print,
+            "punct" => Named::Punct,
+            "space" => Named::Space,
+            "upper" => Named::Upper,
+            "xdigit" => Named::Xdigit,
+            _ => return None,
+        })
+    }
+
+    fn matches(self, c: char) -> bool {
+        match self {
+            Named::Alnum => c.is_alphanumeric(),
+            Named::Alpha => c.is_alphabetic(),
+            Named::Blank => c == ' ' || c == '\t',
+            Named::Cntrl => c.is_control(),
+            Named::Digit => c.is_ascii_digit(),
+            Named::Graph => !c.is_control() && !c.is_whitespace(),
+            Named::Lower => c.is_lowercase(),
+            Named::Print => !c.is_control(),
+            Named::Punct => c.is_ascii_punctuation(),
+            Named::Space => c.is_whitespace(),
+            Named::Upper => c.is_uppercase(),
+            Named::Xdigit => c.is_ascii_hexdigit(),
+        }
+    }
+}
+
+/// One member of a bracket expression.
+#[derive(Clone, Debug, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    Named(Named),
+    /// A byte that isn't valid UTF-8
+    Byte(u8),
+}
+
+/// A bracket expression like `[a-z_]` or `[^[:space:]]`.
+#[derive(Clone, Debug, PartialEq)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+impl Class {
+    fn named(named: Named, negated: bool) -> Class {
+        Class {
+            negated,
+            items: vec![ClassItem::Named(named)],
+        }
+    }
+
+    fn with_underscore(mut self) -> Class {
+        self.items.push(ClassItem::Range('_', '_'));
+        self
+    }
+
+    fn contains(&self, c: char) -> bool {
+        self.items.iter().any(|item| match *item {
+            ClassItem::Range(lo, hi) => lo <= c && c <= hi,
+            ClassItem::Named(n) => n.matches(c),
+            ClassItem::Byte(_) => false,
+        })
+    }
+
+    /// Does this match *byte*, which isn't part of any character?
+    fn matches_byte(&self, byte: u8) -> bool {
+        self.items.contains(&ClassItem::Byte(byte)) != self.negated
+    }
+
+    fn matches(&self, c: char, icase: bool) -> bool {
+        let found = if icase {
+            self.contains(c) || self.contains(lower(c)) || self.contains(upper(c))
+        } else {
+            self.contains(c)
+        };
+        found != self.negated
+    }
+}
+
+/// Zero-width assertions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Look {
+    LineStart,
+    LineEnd,
+    WordBoundary,
+    NotWordBoundary,
+    WordStart,
+    WordEnd,
+    /// Not just after a word character, for -w
+    NotAfterWord,
+    /// Not just before a word character, for -w
+    NotBeforeWord,
+}
+
+/// The parsed form of a pattern.
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Empty,
+    Literal(char),
+    /// A byte in the pattern that isn't valid UTF-8, which only matches itself
+    Byte(u8),
+    Any,
+    Class(Class),
+    Look(Look),
+    Group(Box<Node>, usize),
+    Backref(usize),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat(Box<Node>, u32, Option<u32>),
+}
+
+impl Node {
+    /// The string this node matches, if it only ever matches one. Not for a byte on its own,
+    /// which a substring search could find in the middle of a character.
+    fn literal(&self) -> Option<Vec<u8>> {
+        match *self {
+            Node::Empty => Some(Vec::new()),
+            Node::Literal(c) => Some(c.to_string().into_bytes()),
+            Node::Concat(ref nodes) => nodes.iter().map(Node::literal).collect::<Option<Vec<_>>>().map(|s| s.concat()),
+            _ => None,
+        }
+    }
+
+    /// The longest string that every match of this node has in it, if there is one.
+    fn required(&self) -> Option<Vec<u8>> {
+        match *self {
+            Node::Literal(c) => Some(c.to_string().into_bytes()),
+            Node::Byte(b) => Some(vec![b]),
+            Node::Group(ref inner, _) => inner.required(),
+            Node::Repeat(ref inner, min, _) if min > 0 => inner.required(),
+            Node::Concat(ref nodes) => {
+                let mut best: Option<Vec<u8>> = None;
+                let mut run = Vec::new();
+                for node in nodes {
+                    let found = match node.literal() {
+                        Some(s) => {
+                            run.extend_from_slice(&s);
+                            Some(run.clone())
+                        },
+                        None => {
+                            run.clear();
+                            node.required()
+                        },
+                    };
+                    if let Some(found) = found {
+                        if best.as_ref().is_none_or(|b| found.len() > b.len()) {
+                            best = Some(found);
+                        }
+                    }
+                }
+                best
+            },
+            _ => None,
+        }
+    }
+
+    /// Adds what each group in this node matches to *groups*, by number.
+    fn groups(&self, groups: &mut HashMap<usize, Node>) {
+        match *self {
+            Node::Group(ref inner, index) => {
+                groups.insert(index, (**inner).clone());
+                inner.groups(groups);
+            },
+            Node::Repeat(ref inner, _, _) => inner.groups(groups),
+            Node::Concat(ref nodes) | Node::Alternate(ref nodes) => {
+                for n in nodes {
+                    n.groups(groups);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// This node with each back-reference swapped for the group it refers to, which matches
+    /// whatever the back-reference can and more, with no back-references left to need the
+    /// backtracker. The group's anchors and word assertions held where it matched, not where
+    /// the back-reference is, so they are left out of the copy. None if that comes to more
+    /// than *budget* nodes, as it can when groups refer back to groups that refer back in turn.
+    fn relaxed(&self, groups: &HashMap<usize, Node>, copy: bool, budget: &mut usize) -> Option<Node> {
+        *budget = budget.checked_sub(1)?;
+        let relax = |nodes: &[Node], budget: &mut usize| -> Option<Vec<Node>> {
+            nodes.iter().map(|n| n.relaxed(groups, copy, budget)).collect()
+        };
+        Some(match *self {
+            // a back-reference can only refer to a group closed before it, so this ends
+            Node::Backref(n) => groups.get(&n)?.relaxed(groups, true, budget)?,
+            Node::Look(_) if copy => Node::Empty,
+            Node::Group(ref inner, index) => Node::Group(Box::new(inner.relaxed(groups, copy, budget)?), index),
+            Node::Repeat(ref inner, min, max) => Node::Repeat(Box::new(inner.relaxed(groups, copy, budget)?), min, max),
+            Node::Concat(ref nodes) => Node::Concat(relax(nodes, budget)?),
+            Node::Alternate(ref nodes) => Node::Alternate(relax(nodes, budget)?),
+            ref node => node.clone(),
+        })
+    }
+}
+
+/// Turns the characters of a pattern into a `Node` tree.
+struct Parser {
+    chars: Vec<char>,
+    /// The byte each character stands for, where the pattern has one that isn't valid UTF-8
+    raw: Vec<Option<u8>>,
+    pos: usize,
+    syntax: Syntax,
+    depth: usize,
+    groups: usize,
+    closed: Vec<bool>,
+    /// Groups used by the patterns compiled before this one
+    base: usize,
+}
+
+impl Parser {
+    fn new(pattern: &[u8], syntax: Syntax) -> Parser {
+        let mut chars = Vec::new();
+        let mut raw = Vec::new();
+        let mut pos = 0;
+        while pos < pattern.len() {
+            let (c, len) = decode(pattern, pos);
+            chars.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+            raw.push(if c.is_none() { Some(pattern[pos]) } else { None });
+            pos += len;
+        }
+        Parser {
+            chars,
+            raw,
+            pos: 0,
+            syntax,
+            depth: 0,
+            groups: 0,
+            closed: vec![false],
+            base: 0,
+        }
+    }
+
+    fn parse(&mut self) -> ParseResult<Node> {
+        if self.syntax == Syntax::Fixed {
+            let lits = (0..self.chars.len()).map(|i| self.literal(i)).collect();
+            return Ok(Node::Concat(lits));
+        }
+        let node = self.parse_alternation()?;
+        if self.pos < self.chars.len() {
+            // only an unbalanced close paren can stop the top level early
+            return Err(Error::new("Unmatched ) or \\)"));
+        }
+        Ok(node)
+    }
+
+    /// The character at *at* as a literal, or the byte it stands for.
+    fn literal(&self, at: usize) -> Node {
+        match self.raw[at] {
+            Some(b) => Node::Byte(b),
+            None => Node::Literal(self.chars[at]),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.chars.get(self.pos + n).cloned()
+    }
+
+    fn looking_at(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.peek_at(i) == Some(c))
+    }
+
+    fn at_alternation(&self) -> bool {
+        match self.syntax {
+            Syntax::Extended => self.looking_at("|"),
+            _ => self.looking_at("\\|"),
+        }
+    }
+
+    fn at_close(&self) -> bool {
+        match self.syntax {
+            Syntax::Extended => self.depth > 0 && self.looking_at(")"),
+            _ => self.looking_at("\\)"),
+        }
+    }
+
+    /// Each branch can only refer back to groups closed before the alternation started, or in
+    /// the branch itself, as with GNU. After it, groups from every branch may be referred to.
+    fn parse_alternation(&mut self) -> ParseResult<Node> {
+        let first = self.closed.len();
+        let mut branches = vec![self.parse_concat()?];
+        while self.at_alternation() {
+            self.pos += if self.syntax == Syntax::Extended { 1 } else { 2 };
+            let hidden: Vec<usize> = (first..self.closed.len()).filter(|&i| self.closed[i]).collect();
+            for &i in &hidden {
+                self.closed[i] = false;
+            }
+            branches.push(self.parse_concat()?);
+            for &i in &hidden {
+                self.closed[i] = true;
+            }
+        }
+        if branches.len() == 1 {
+            Ok(branches.pop().unwrap())
+        } else {
+            Ok(Node::Alternate(branches))
+        }
+    }
+
+    fn parse_concat(&mut self) -> ParseResult<Node> {
+        let mut items: Vec<Node> = Vec::new();
+        while self.pos < self.chars.len() && !self.at_alternation() && !self.at_close() {
+            let atom = self.parse_atom(&items)?;
+            let atom = self.parse_repeats(atom)?;
+            items.push(atom);
+        }
+        match items.len() {
+            0 => Ok(Node::Empty),
+            1 => Ok(items.pop().unwrap()),
+            _ => Ok(Node::Concat(items)),
+        }
+    }
+
+    /// In basic syntax a `$` is only an anchor at the end of the pattern or of a branch.
+    fn dollar_is_anchor(&self) -> bool {
+        if self.syntax == Syntax::Extended {
+            return true;
+        }
+        let rest = self.pos;
+        rest == self.chars.len()
+            || (self.chars.get(rest) == Some(&'\\')
+                && (self.chars.get(rest + 1) == Some(&')') || self.chars.get(rest + 1) == Some(&'|')))
+    }
+
+    fn parse_atom(&mut self, before: &[Node]) -> ParseResult<Node> {
+        let at_start = before.is_empty() || (before.len() == 1 && before[0] == Node::Look(Look::LineStart));
+        let c = self.chars[self.pos];
+        self.pos += 1;
+
+        match c {
+            '.' => Ok(Node::Any),
+            '[' => self.parse_bracket(),
+            '^' if self.syntax == Syntax::Extended || before.is_empty() => Ok(Node::Look(Look::LineStart)),
+            '$' if self.dollar_is_anchor() => {
+                Ok(Node::Look(Look::LineEnd))
+            },
+            '*' if at_start => Ok(Node::Literal('*')),
+            '+' | '?' | '{' if self.syntax == Syntax::Extended && at_start => Ok(Node::Literal(c)),
+            '(' if self.syntax == Syntax::Extended => self.parse_group(),
+            '\\' => self.parse_escape(at_start),
+            _ => Ok(self.literal(self.pos - 1)),
+        }
+    }
+
+    fn parse_escape(&mut self, at_start: bool) -> ParseResult<Node> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err(Error::new("Trailing backslash")),
+        };
+        self.pos += 1;
+
+        match c {
+            '(' if self.syntax == Syntax::Basic => self.parse_group(),
+            '{' if self.syntax == Syntax::Basic && at_start => Ok(Node::Literal('{')),
+            '1'..='9' => {
+                let n = c.to_digit(10).unwrap() as usize;
+                if n > self.groups || !self.closed[n] {
+                    return Err(Error::new("Invalid back reference"));
+                }
+                Ok(Node::Backref(self.base + n))
+            },
+            'w' => Ok(Node::Class(Class::named(Named::Alnum, false).with_underscore())),
+            'W' => Ok(Node::Class(Class::named(Named::Alnum, true).with_underscore())),
+            's' => Ok(Node::Class(Class::named(Named::Space, false))),
+            'S' => Ok(Node::Class(Class::named(Named::Space, true))),
+            'b' => Ok(Node::Look(Look::WordBoundary)),
+            'B' => Ok(Node::Look(Look::NotWordBoundary)),
+            '<' => Ok(Node::Look(Look::WordStart)),
+            '>' => Ok(Node::Look(Look::WordEnd)),
+            '`' => Ok(Node::Look(Look::LineStart)),
+            '\'' => Ok(Node::Look(Look::LineEnd)),
+            _ => Ok(self.literal(self.pos - 1)),
+        }
+    }
+
+    fn parse_group(&mut self) -> ParseResult<Node> {
+        self.groups += 1;
+        let index = self.groups;
+        self.closed.push(false);
+        self.depth += 1;
+        let inner = self.parse_alternation()?;
+        self.depth -= 1;
+
+        let close = if self.syntax == Syntax::Extended { ")" } else { "\\)" };
+        if !self.looking_at(close) {
+            return Err(Error::new("Unmatched ( or \\("));
+        }
+        self.pos += close.len();
+        self.closed[index] = true;
+        Ok(Node::Group(Box::new(inner), self.base + index))
+    }
+
+    fn parse_repeats(&mut self, mut atom: Node) -> ParseResult<Node> {
+        loop {
+            let extended = self.syntax == Syntax::Extended;
+            let (min, max) = match self.peek() {
+                Some('*') => {
+                    self.pos += 1;
+                    (0, None)
+                },
+                Some('+') if extended => {
+                    self.pos += 1;
+                    (1, None)
+                },
+                Some('?') if extended => {
+                    self.pos += 1;
+                    (0, Some(1))
+                },
+                Some('{') if extended => {
+                    let save = self.pos;
+                    self.pos += 1;
+                    match self.parse_interval()? {
+                        Some(bounds) => bounds,
+                        None => {
+                            // not an interval after all, so the brace is an ordinary character
+                            self.pos = save;
+                            return Ok(atom);
+                        }
+                    }
+                },
+                Some('\\') if !extended => match self.peek_at(1) {
+                    Some('+') => {
+                        self.pos += 2;
+                        (1, None)
+                    },
+                    Some('?') => {
+                        self.pos += 2;
+                        (0, Some(1))
+                    },
+                    Some('{') => {
+                        self.pos += 2;
+                        match self.parse_interval()? {
+                            Some(bounds) => bounds,
+                            None => return Err(Error::new("Invalid content of \\{\\}")),
+                        }
+                    },
+                    _ => return Ok(atom),
+                },
+                _ => return Ok(atom),
+            };
+            atom = Node::Repeat(Box::new(atom), min, max);
+        }
+    }
+
+    fn parse_number(&mut self) -> ParseResult<Option<u32>> {
+        let mut n: Option<u32> = None;
+        while let Some(d) = self.peek().and_then(|c| c.to_digit(10)) {
+            let v = n.unwrap_or(0) * 10 + d;
+            if v > DUP_MAX {
+                return Err(Error::new("Regular expression too big"));
+            }
+            n = Some(v);
+            self.pos += 1;
+        }
+        Ok(n)
+    }
+
+    /// Parses the inside of an interval, after the opening brace. Returns None if this is not a
+    /// well-formed interval, which is an error in basic syntax and a literal brace in extended.
+    fn parse_interval(&mut self) -> ParseResult<Option<(u32, Option<u32>)>> {
+        let min = self.parse_number()?;
+        let max = if self.peek() == Some(',') {
+            self.pos += 1;
+            self.parse_number()?
+        } else {
+            match min {
+                Some(m) => Some(m),
+                None => return Ok(None),
+            }
+        };
+
+        let close = if self.syntax == Syntax::Extended { "}" } else { "\\}" };
+        if !self.looking_at(close) {
+            if self.syntax == Syntax::Basic && self.pos >= self.chars.len() {
+                return Err(Error::new("Unmatched \\{"));
+            }
+            return Ok(None);
+        }
+        self.pos += close.len();
+
+        let min = min.unwrap_or(0);
+        if let Some(max) = max {
+            if max < min {
+                return Err(Error::new("Invalid content of \\{\\}"));
+            }
+        }
+        Ok(Some((min, max)))
+    }
+
+    fn parse_bracket(&mut self) -> ParseResult<Node> {
+        let unmatched = || Error::new("Unmatched [, [^, [:, [., or [=");
+        let open = self.pos;
+        let mut class = Class {
+            negated: false,
+            items: Vec::new(),
+        };
+        if self.peek() == Some('^') {
+            class.negated = true;
+            self.pos += 1;
+        }
+
+        let mut first = true;
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(unmatched()),
+            };
+            if c == ']' && !first {
+                self.pos += 1;
+                break;
+            }
+            first = false;
+
+            let lo = if c == '[' && (self.peek_at(1) == Some(':') || self.peek_at(1) == Some('=') || self.peek_at(1) == Some('.')) {
+                let kind = self.peek_at(1).unwrap();
+                self.pos += 2;
+                let start = self.pos;
+                while !(self.peek() == Some(kind) && self.peek_at(1) == Some(']')) {
+                    if self.pos >= self.chars.len() {
+                        return Err(unmatched());
+                    }
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                self.pos += 2;
+
+                if kind == ':' {
+                    match Named::from_name(&name) {
+                        Some(n) => class.items.push(ClassItem::Named(n)),
+                        None => return Err(Error::new("Invalid character class name")),
+                    }
+                    continue;
+                }
+                // equivalence classes and collating symbols only name single characters here
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => ch,
+                    _ => return Err(Error::new("Invalid collation character")),
+                }
+            } else if let Some(b) = self.raw[self.pos] {
+                // a byte that isn't a character only stands for itself, never a range
+                self.pos += 1;
+                class.items.push(ClassItem::Byte(b));
+                continue;
+            } else {
+                self.pos += 1;
+                c
+            };
+
+            // a range, unless the dash is the last thing in the bracket
+            if self.peek() == Some('-') && self.peek_at(1).is_some() && self.peek_at(1) != Some(']') {
+                self.pos += 1;
+                let hi = if self.looking_at("[.") {
+                    self.pos += 2;
+                    let hi = self.peek().ok_or_else(unmatched)?;
+                    if !self.looking_at_offset(1, ".]") {
+                        return Err(Error::new("Invalid collation character"));
+                    }
+                    self.pos += 3;
+                    hi
+                } else {
+                    if self.raw[self.pos].is_some() {
+                        return Err(Error::new("Invalid range end"));
+                    }
+                    let hi = self.chars[self.pos];
+                    self.pos += 1;
+                    hi
+                };
+                if hi < lo {
+                    return Err(Error::new("Invalid range end"));
+                }
+                class.items.push(ClassItem::Range(lo, hi));
+            } else {
+                class.items.push(ClassItem::Range(lo, lo));
+            }
+        }
+
+        // GNU rejects "[:space:]", which is almost always a typo for "[[:space:]]"
+        let body: String = self.chars[open..self.pos - 1].iter().collect();
+        if body.len() > 1 && body.starts_with(':') && body.ends_with(':') {
+            return Err(Error::new("character class syntax is [[:space:]], not [:space:]"));
+        }
+        Ok(Node::Class(class))
+    }
+
+    fn looking_at_offset(&self, n: usize, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.peek_at(n + i) == Some(c))
+    }
+}
+
+/// Instructions for the matching virtual machine.
+#[derive(Clone, Debug, PartialEq)]
+enum Inst {
+    Char(char),
+    Byte(u8),
+    Any,
+    Class(usize),
+    Look(Look),
+    Split(usize, usize),
+    Jmp(usize),
+    Save(usize),
+    Backref(usize),
+    /// Remember where an unbounded loop iteration started
+    Mark(usize),
+    /// Leave the loop if the iteration that started at the matching Mark consumed nothing, so an
+    /// empty iteration can happen once, to set its groups, but never over and over. The loop's
+    /// jump back always comes straight after this.
+    Progress(usize),
+    Match,
+}
+
+/// Compiles a `Node` tree into a list of instructions.
+struct Compiler {
+    insts: Vec<Inst>,
+    classes: Vec<Class>,
+    marks: usize,
+    icase: bool,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> ParseResult<usize> {
+        if self.insts.len() >= MAX_INSTS {
+            return Err(Error::new("Regular expression too big"));
+        }
+        self.insts.push(inst);
+        Ok(self.insts.len() - 1)
+    }
+
+    fn patch(&mut self, at: usize, target: usize) {
+        match self.insts[at] {
+            Inst::Split(_, ref mut b) => *b = target,
+            Inst::Jmp(ref mut t) => *t = target,
+            _ => unreachable!(),
+        }
+    }
+
+    fn compile(&mut self, node: &Node) -> ParseResult<()> {
+        match *node {
+            Node::Empty => {},
+            Node::Literal(c) => {
+                let c = if self.icase { lower(c) } else { c };
+                self.push(Inst::Char(c))?;
+            },
+            Node::Byte(b) => {
+                self.push(Inst::Byte(b))?;
+            },
+            Node::Any => {
+                self.push(Inst::Any)?;
+            },
+            Node::Class(ref class) => {
+                self.classes.push(class.clone());
+                let index = self.classes.len() - 1;
+                self.push(Inst::Class(index))?;
+            },
+            Node::Look(look) => {
+                self.push(Inst::Look(look))?;
+            },
+            Node::Group(ref inner, index) => {
+                self.push(Inst::Save(index * 2))?;
+                self.compile(inner)?;
+                self.push(Inst::Save(index * 2 + 1))?;
+            },
+            Node::Backref(index) => {
+                self.push(Inst::Backref(index))?;
+            },
+            Node::Concat(ref nodes) => {
+                for n in nodes {
+                    self.compile(n)?;
+                }
+            },
+            Node::Alternate(ref branches) => {
+                let mut jumps = Vec::new();
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 < branches.len() {
+                        let split = self.push(Inst::Split(self.insts.len() + 1, 0))?;
+                        self.compile(branch)?;
+                        jumps.push(self.push(Inst::Jmp(0))?);
+                        let next = self.insts.len();
+                        self.patch(split, next);
+                    } else {
+                        self.compile(branch)?;
+                    }
+                }
+                let end = self.insts.len();
+                for j in jumps {
+                    self.patch(j, end);
+                }
+            },
+            Node::Repeat(ref inner, min, max) => {
+                for _ in 0..min {
+                    self.compile(inner)?;
+                }
+                match max {
+                    None => {
+                        let mark = self.marks;
+                        self.marks += 1;
+                        let split = self.push(Inst::Split(self.insts.len() + 1, 0))?;
+                        self.push(Inst::Mark(mark))?;
+                        self.compile(inner)?;
+                        self.push(Inst::Progress(mark))?;
+                        self.push(Inst::Jmp(split))?;
+                        let end = self.insts.len();
+                        self.patch(split, end);
+                    },
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in min..max {
+                            splits.push(self.push(Inst::Split(self.insts.len() + 1, 0))?);
+                            self.compile(inner)?;
+                        }
+                        let end = self.insts.len();
+                        for s in splits {
+                            self.patch(s, end);
+                        }
+                    },
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+/// A compiled pattern.
+#[derive(Clone, Debug)]
+pub struct Regex {
+    insts: Vec<Inst>,
+    classes: Vec<Class>,
+    /// Capture and loop-mark slots needed by the backtracker
+    slots: usize,
+    marks: usize,
+    backrefs: bool,
+    /// The capture slots that back-references read, which the backtracker has to remember
+    referenced: Vec<usize>,
+    /// A string every match has in it, so lines without it needn't be backtracked through
+    required: Option<Finder<'static>>,
+    /// The pattern with its back-references relaxed, for the Pike VM to rule out lines and find
+    /// the first place a match could start before backtracking
+    relaxed: Option<Box<Regex>>,
+    icase: bool,
+    /// Set when every pattern is a fixed string, and used instead of the program
+    literals: Option<Literals>,
+    /// Set when there are several patterns with strings they need, and used instead of the program
+    branches: Option<Box<Branches>>,
+    /// What has to be around a match of the strings, for -w and -x
+    bounds: Bounds,
+}
+
+/// Where a match may be, for -w and -x.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bounds {
+    Any,
+    /// With no word characters just before or after it
+    Word,
+    /// Taking up the whole line
+    Line,
+}
+
+/// Patterns compiled one by one, each of them behind a string every match of it has in it, along
+/// with the rest of the patterns compiled together.
+#[derive(Clone, Debug)]
+struct Branches {
+    /// Finds each place one of the strings is
+    automaton: AhoCorasick,
+    /// The patterns in `filtered` that need each of the strings in the automaton
+    owners: Vec<Vec<usize>>,
+    filtered: Vec<Regex>,
+    /// The patterns without a string to look for, which every text is run through
+    rest: Option<Regex>,
+}
+
+impl Branches {
+    /// The patterns in `filtered` that could match at or after *start* in *text*.
+    fn candidates(&self, text: &[u8], start: usize) -> Vec<usize> {
+        let mut found: Vec<usize> = self.automaton.find_overlapping_iter(&text[start..])
+            .flat_map(|m| self.owners[m.pattern()].iter().copied())
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    fn is_match(&self, text: &[u8]) -> bool {
+        self.rest.as_ref().is_some_and(|r| r.is_match(text))
+            || self.candidates(text, 0).into_iter().any(|i| self.filtered[i].is_match(text))
+    }
+
+    /// The leftmost of the matches of each pattern, and the longest of those that start there.
+    fn find_at(&self, text: &[u8], start: usize) -> Option<(usize, usize)> {
+        let mut best = self.rest.as_ref().and_then(|r| r.find_at(text, start));
+        for i in self.candidates(text, start) {
+            if let Some((s, e)) = self.filtered[i].find_at(text, start) {
+                if best.is_none_or(|(bs, be)| s < bs || (s == bs && e > be)) {
+                    best = Some((s, e));
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Searchers for patterns that are only strings.
+#[derive(Clone, Debug)]
+enum Literals {
+    One(Finder<'static>),
+    Many {
+        /// Finds the leftmost-longest string, or for -w, every string, overlapping or not
+        automaton: AhoCorasick,
+        /// Whether one of the strings is empty. They are left out of the automaton, which doesn't
+        /// always find them.
+        empty: bool,
+    },
+}
+
+impl Regex {
+    /// Compiles *pattern* using the given *syntax*.
+    pub fn new<P: AsRef<[u8]>>(pattern: P, syntax: Syntax) -> Result<Regex, Error> {
+        Regex::with_config(pattern, &Config { syntax, ..Default::default() })
+    }
+
+    /// Compiles *pattern* with all of the settings in *config*.
+    pub fn with_config<P: AsRef<[u8]>>(pattern: P, config: &Config) -> Result<Regex, Error> {
+        Regex::with_patterns(&[pattern], config)
+    }
+
+    /// Compiles *patterns* into one regex that matches wherever any of them does. Each pattern
+    /// numbers its own groups for back-references. With no patterns at all, nothing matches.
+    pub fn with_patterns<P: AsRef<[u8]>>(patterns: &[P], config: &Config) -> Result<Regex, Error> {
+        let mut branches = Vec::new();
+        let mut groups = 0;
+        for pattern in patterns {
+            let mut parser = Parser::new(pattern.as_ref(), config.syntax);
+            parser.base = groups;
+            branches.push(parser.parse()?);
+            groups += parser.groups;
+        }
+
+        let bounds = if config.line {
+            Bounds::Line
+        } else if config.word {
+            Bounds::Word
+        } else {
+            Bounds::Any
+        };
+
+        // Aho-Corasick only folds ASCII letters, so other case-insensitive strings go through
+        // the program like any other pattern
+        let strings: Option<Vec<Vec<u8>>> = branches.iter().map(Node::literal).collect();
+        let literals = match strings {
+            Some(ref strings) if !config.ignore_case && strings.len() == 1 => {
+                Some(Literals::One(Finder::new(&strings[0]).into_owned()))
+            },
+            Some(ref strings) if !config.ignore_case || strings.iter().all(|s| s.is_ascii()) => {
+                let kind = if bounds == Bounds::Word { MatchKind::Standard } else { MatchKind::LeftmostLongest };
+                let automaton = AhoCorasickBuilder::new()
+                    .match_kind(kind)
+                    .ascii_case_insensitive(config.ignore_case)
+                    .dfa(true)
+                    .build(strings.iter().filter(|s| !s.is_empty()));
+                Some(Literals::Many {
+                    automaton,
+                    empty: strings.iter().any(|s| s.is_empty()),
+                })
+            },
+            _ => None,
+        };
+        if let Some(literals) = literals {
+            return Ok(Regex::literal(Some(literals), config.ignore_case, bounds));
+        }
+
+        let wrap = |node: Node| if config.line {
+            Node::Concat(vec![Node::Look(Look::LineStart), node, Node::Look(Look::LineEnd)])
+        } else if config.word {
+            Node::Concat(vec![Node::Look(Look::NotAfterWord), node, Node::Look(Look::NotBeforeWord)])
+        } else {
+            node
+        };
+        let together = |mut nodes: Vec<Node>| {
+            let node = if nodes.len() == 1 { nodes.remove(0) } else { Node::Alternate(nodes) };
+            Regex::compile(&wrap(node), groups, config.ignore_case)
+        };
+
+        if branches.len() < 2 {
+            return together(branches);
+        }
+
+        // a long list of patterns makes for a program too slow to run every line through, so
+        // each pattern that needs a string is only run where its string is. As with the strings
+        // above, only ASCII can be found whatever its case.
+        let mut strings: Vec<Vec<u8>> = Vec::new();
+        let mut index: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut owners: Vec<Vec<usize>> = Vec::new();
+        let mut filtered = Vec::new();
+        let mut rest = Vec::new();
+        for node in branches {
+            let required = node.required().filter(|s| !s.is_empty() && (!config.ignore_case || s.is_ascii()));
+            let required = match required {
+                Some(required) => required,
+                None => {
+                    rest.push(node);
+                    continue;
+                },
+            };
+            let at = *index.entry(required.clone()).or_insert_with(|| {
+                strings.push(required);
+                owners.push(Vec::new());
+                strings.len() - 1
+            });
+            owners[at].push(filtered.len());
+            filtered.push(Regex::compile(&wrap(node), groups, config.ignore_case)?);
+        }
+        if filtered.is_empty() {
+            return together(rest);
+        }
+        let automaton = AhoCorasickBuilder::new()
+            .ascii_case_insensitive(config.ignore_case)
+            .dfa(true)
+            .build(&strings);
+        let rest = if rest.is_empty() { None } else { Some(together(rest)?) };
+        let mut regex = Regex::literal(None, config.ignore_case, bounds);
+        regex.branches = Some(Box::new(Branches { automaton, owners, filtered, rest }));
+        Ok(regex)
+    }
+
+    /// A regex with no program of its own, which searches with *literals* or whatever is set
+    /// on it afterwards instead.
+    fn literal(literals: Option<Literals>, icase: bool, bounds: Bounds) -> Regex {
+        Regex {
+            insts: Vec::new(),
+            classes: Vec::new(),
+            slots: 0,
+            marks: 0,
+            backrefs: false,
+            referenced: Vec::new(),
+            required: None,
+            relaxed: None,
+            icase,
+            literals,
+            branches: None,
+            bounds,
+        }
+    }
+
+    fn compile(node: &Node, groups: usize, icase: bool) -> Result<Regex, Error> {
+        let mut compiler = Compiler {
+            insts: Vec::new(),
+            classes: Vec::new(),
+            marks: 0,
+            icase,
+        };
+        compiler.compile(node)?;
+        compiler.push(Inst::Match)?;
+
+        let mut referenced: Vec<usize> = compiler.insts.iter()
+            .filter_map(|i| match *i {
+                Inst::Backref(n) => Some(n),
+                _ => None,
+            })
+            .flat_map(|n| vec![n * 2, n * 2 + 1])
+            .collect();
+        referenced.sort_unstable();
+        referenced.dedup();
+        let backrefs = !referenced.is_empty();
+        // case-insensitive text could spell the string differently
+        let required = match node.required() {
+            Some(ref s) if backrefs && !icase && !s.is_empty() => Some(Finder::new(s).into_owned()),
+            _ => None,
+        };
+        let relaxed = if backrefs {
+            let mut found = HashMap::new();
+            node.groups(&mut found);
+            let mut budget = MAX_INSTS;
+            node.relaxed(&found, false, &mut budget)
+                .and_then(|node| Regex::compile(&node, groups, icase).ok())
+                .map(Box::new)
+        } else {
+            None
+        };
+        Ok(Regex {
+            insts: compiler.insts,
+            classes: compiler.classes,
+            slots: (groups + 1) * 2,
+            marks: compiler.marks,
+            backrefs,
+            referenced,
+            required,
+            relaxed,
+            icase,
+            literals: None,
+            branches: None,
+            bounds: Bounds::Any,
+        })
+    }
+
+    /// Is every pattern a plain string? Matches of these can't span more than one line, and are
+    /// quick to find in a large buffer.
+    pub fn is_literal(&self) -> bool {
+        self.literals.is_some() && self.bounds == Bounds::Any
+    }
+
+    /// Returns true if *text* contains a match anywhere.
+    pub fn is_match(&self, text: &[u8]) -> bool {
+        if let Some(ref branches) = self.branches {
+            branches.is_match(text)
+        } else if self.literals.is_some() {
+            self.find_at(text, 0).is_some()
+        } else if self.backrefs {
+            self.find_backtrack(text, 0, true).is_some()
+        } else {
+            self.pike(text, 0, true).is_some()
+        }
+    }
+
+    /// Finds the leftmost-longest match in *text* starting at or after byte offset *start*, and
+    /// returns its start and end offsets.
+    pub fn find_at(&self, text: &[u8], start: usize) -> Option<(usize, usize)> {
+        if let Some(ref branches) = self.branches {
+            return branches.find_at(text, start);
+        }
+        if let Some(ref literals) = self.literals {
+            return self.find_literal(literals, text, start);
+        }
+        if self.backrefs {
+            self.find_backtrack(text, start, false)
+        } else {
+            self.pike(text, start, false)
+        }
+    }
+
+    /// Finds the leftmost-longest place at or after *start* where one of the strings is, with
+    /// what -w or -x want around it.
+    fn find_literal(&self, literals: &Literals, text: &[u8], start: usize) -> Option<(usize, usize)> {
+        let fits = |s: usize, e: usize| match self.bounds {
+            Bounds::Any => true,
+            // an empty string can be found in the middle of a character, but not matched there
+            Bounds::Word => {
+                (e > s || is_boundary(text, s)) && check_look(Look::NotAfterWord, text, s) && check_look(Look::NotBeforeWord, text, e)
+            },
+            Bounds::Line => s == 0 && e == text.len(),
+        };
+        match *literals {
+            Literals::One(ref finder) => {
+                let len = finder.needle().len();
+                let mut pos = start;
+                while pos <= text.len() {
+                    let s = pos + finder.find(&text[pos..])?;
+                    if fits(s, s + len) {
+                        return Some((s, s + len));
+                    }
+                    if self.bounds == Bounds::Line {
+                        return None;
+                    }
+                    pos = s + 1;
+                }
+                None
+            },
+            Literals::Many { ref automaton, empty } if self.bounds == Bounds::Word => {
+                // a string that doesn't fit can overlap a shorter one that does, so every place
+                // each string turns up is tried. They come in order of where they end, so one
+                // that begins further left can turn up until they end more than the longest
+                // string past the best start.
+                let mut best = if empty { Boundaries::new(text, start).find(|&p| fits(p, p)).map(|p| (p, p)) } else { None };
+                for m in automaton.find_overlapping_iter(&text[start..]) {
+                    let (s, e) = (start + m.start(), start + m.end());
+                    if best.is_some_and(|(b, _)| e > b + automaton.max_pattern_len()) {
+                        break;
+                    }
+                    if fits(s, e) && best.is_none_or(|(bs, be)| s < bs || (s == bs && e > be)) {
+                        best = Some((s, e));
+                    }
+                }
+                best
+            },
+            Literals::Many { ref automaton, empty } => {
+                // for -x, nothing but the leftmost-longest string could take up the whole line
+                let found = match automaton.find(&text[start..]).map(|m| (start + m.start(), start + m.end())) {
+                    Some((s, e)) if s == start || !empty => Some((s, e)),
+                    // the empty string is further left
+                    _ if empty => Some((start, start)),
+                    _ => None,
+                };
+                found.filter(|&(s, e)| fits(s, e))
+            },
+        }
+    }
+
+    /// Iterates over all non-overlapping, non-empty matches in *text*.
+    pub fn find_iter<'r, 't>(&'r self, text: &'t [u8]) -> Matches<'r, 't> {
+        Matches {
+            regex: self,
+            text,
+            pos: 0,
+        }
+    }
+
+    /// Does the consuming instruction at *pc* accept character *c*? None is a *byte* that isn't
+    /// valid UTF-8.
+    fn step(&self, pc: usize, c: Option<char>, byte: u8) -> bool {
+        let c = match c {
+            Some(c) => c,
+            None => return match self.insts[pc] {
+                Inst::Any => true,
+                Inst::Byte(want) => byte == want,
+                Inst::Class(i) => self.classes[i].matches_byte(byte),
+                _ => false,
+            },
+        };
+        match self.insts[pc] {
+            Inst::Char(want) => {
+                c == want || (self.icase && lower(c) == want)
+            },
+            Inst::Any => c != '\n',
+            Inst::Class(i) => self.classes[i].matches(c, self.icase),
+            _ => false,
+        }
+    }
+
+    /// Runs the Pike VM from *start*. Threads carry the position their match began at, and are
+    /// kept in order of that position, so the first thread to reach a state is always the
+    /// leftmost one. Once a match is found no new threads are started, and the remaining threads
+    /// that began at the same place are allowed to run on to find the longest match.
+    fn pike(&self, text: &[u8], start: usize, quick: bool) -> Option<(usize, usize)> {
+        let mut clist = Threads::new(self.insts.len());
+        let mut nlist = Threads::new(self.insts.len());
+        let mut stack = Vec::new();
+        let mut best: Option<(usize, usize)> = None;
+        let mut pos = start;
+
+        loop {
+            if best.is_none() {
+                self.add(&mut clist, &mut stack, 0, pos, text, pos);
+            }
+            if clist.dense.is_empty() {
+                break;
+            }
+
+            let (c, len) = if pos < text.len() { decode(text, pos) } else { (None, 0) };
+            for i in 0..clist.dense.len() {
+                let (pc, from) = clist.dense[i];
+                if let Some((s, _)) = best {
+                    if from > s {
+                        break;
+                    }
+                }
+                match self.insts[pc] {
+                    Inst::Match => {
+                        if quick {
+                            return Some((from, pos));
+                        }
+                        best = match best {
+                            Some((s, e)) if s == from && e >= pos => Some((s, e)),
+                            _ => Some((from, pos)),
+                        };
+                    },
+                    _ => {
+                        if len > 0 && self.step(pc, c, text[pos]) {
+                            self.add(&mut nlist, &mut stack, pc + 1, from, text, pos + len);
+                        }
+                    },
+                }
+            }
+
+            if pos >= text.len() {
+                break;
+            }
+            pos += len;
+            std::mem::swap(&mut clist, &mut nlist);
+            nlist.clear();
+        }
+        best
+    }
+
+    /// Adds the thread at *pc* to *list*, following every empty transition out of it.
+    fn add(&self, list: &mut Threads, stack: &mut Vec<usize>, pc: usize, from: usize, text: &[u8], pos: usize) {
+        stack.push(pc);
+        while let Some(pc) = stack.pop() {
+            if list.contains(pc) {
+                continue;
+            }
+            list.insert(pc, from);
+            match self.insts[pc] {
+                Inst::Jmp(t) => stack.push(t),
+                Inst::Split(a, b) => {
+                    stack.push(b);
+                    stack.push(a);
+                },
+                Inst::Save(_) | Inst::Mark(_) | Inst::Progress(_) => stack.push(pc + 1),
+                Inst::Look(look) if check_look(look, text, pos) => stack.push(pc + 1),
+                _ => {},
+            }
+        }
+    }
+
+    /// Tries the backtracker at each place from *start* on, giving the first match it finds.
+    /// Places in the text it has already been, with the same captures, failed from an earlier
+    /// start, so they are remembered across the whole search.
+    fn find_backtrack(&self, text: &[u8], start: usize, quick: bool) -> Option<(usize, usize)> {
+        if let Some(ref required) = self.required {
+            required.find(&text[start..])?;
+        }
+        // no match can start before the first place the relaxed pattern matches
+        let start = match self.relaxed {
+            Some(ref relaxed) => relaxed.pike(text, start, false)?.0,
+            None => start,
+        };
+        let mut seen = HashSet::new();
+        Boundaries::new(text, start).filter_map(|p| self.backtrack(text, p, quick, &mut seen).map(|e| (p, e))).next()
+    }
+
+    /// Backtracks through every path from *start*, returning the end of the longest match, or the
+    /// first one found if *quick* is set. Only used when the pattern has back-references, since
+    /// no automaton can match those. Each split is only followed once for the same place in the
+    /// text, captures that are referred back to, and loops that haven't moved on yet; *seen*
+    /// keeps track of them, which keeps patterns like `\(a*\)*\1` from taking exponential time.
+    fn backtrack(&self, text: &[u8], start: usize, quick: bool, seen: &mut HashSet<State>) -> Option<usize> {
+        enum Job {
+            Explore(usize, usize),
+            Restore(usize, Option<usize>),
+        }
+
+        let mut slots: Vec<Option<usize>> = vec![None; self.slots + self.marks];
+        let mut jobs = vec![Job::Explore(0, start)];
+        let mut best: Option<usize> = None;
+
+        while let Some(job) = jobs.pop() {
+            let (mut pc, mut pos) = match job {
+                Job::Restore(slot, old) => {
+                    slots[slot] = old;
+                    continue;
+                },
+                Job::Explore(pc, pos) => (pc, pos),
+            };
+
+            loop {
+                match self.insts[pc] {
+                    Inst::Match => {
+                        if quick {
+                            return Some(pos);
+                        }
+                        if best.is_none_or(|b| pos > b) {
+                            best = Some(pos);
+                        }
+                        break;
+                    },
+                    Inst::Jmp(t) => pc = t,
+                    Inst::Split(a, b) => {
+                        let state = State {
+                            pc,
+                            pos,
+                            captures: self.referenced.iter().map(|&s| slots[s]).collect(),
+                            // a loop mark before here can never be where a later Progress is
+                            marks: slots[self.slots..].iter().map(|&m| m == Some(pos)).collect(),
+                        };
+                        if !seen.insert(state) {
+                            break;
+                        }
+                        jobs.push(Job::Explore(b, pos));
+                        pc = a;
+                    },
+                    Inst::Save(slot) => {
+                        jobs.push(Job::Restore(slot, slots[slot]));
+                        slots[slot] = Some(pos);
+                        pc += 1;
+                    },
+                    Inst::Mark(m) => {
+                        let slot = self.slots + m;
+                        jobs.push(Job::Restore(slot, slots[slot]));
+                        slots[slot] = Some(pos);
+                        pc += 1;
+                    },
+                    Inst::Progress(m) => {
+                        // step over the jump back to the start of the loop
+                        pc += if slots[self.slots + m] == Some(pos) { 2 } else { 1 };
+                    },
+                    Inst::Look(look) => {
+                        if !check_look(look, text, pos) {
+                            break;
+                        }
+                        pc += 1;
+                    },
+                    Inst::Backref(n) => {
+                        let (s, e) = match (slots[n * 2], slots[n * 2 + 1]) {
+                            (Some(s), Some(e)) => (s, e),
+                            _ => break,
+                        };
+                        match self.match_backref(&text[s..e], text, pos) {
+                            Some(len) => pos += len,
+                            None => break,
+                        }
+                        pc += 1;
+                    },
+                    _ => {
+                        if pos >= text.len() {
+                            break;
+                        }
+                        let (c, len) = decode(text, pos);
+                        if !self.step(pc, c, text[pos]) {
+                            break;
+                        }
+                        pos += len;
+                        pc += 1;
+                    },
+                }
+            }
+        }
+        best
+    }
+
+    /// Matches the text of a captured group again at *pos*, returning how many bytes it took.
+    fn match_backref(&self, group: &[u8], text: &[u8], pos: usize) -> Option<usize> {
+        if !self.icase {
+            return if text[pos..].starts_with(group) { Some(group.len()) } else { None };
+        }
+        let (mut g, mut p) = (0, pos);
+        while g < group.len() {
+            if p >= text.len() {
+                return None;
+            }
+            let (a, alen) = decode(group, g);
+            let (b, blen) = decode(text, p);
+            let same = match (a, b) {
+                (Some(a), Some(b)) => lower(a) == lower(b),
+                _ => group[g] == text[p],
+            };
+            if !same {
+                return None;
+            }
+            g += alen;
+            p += blen;
+        }
+        Some(p - pos)
+    }
+}
+
+/// Where the backtracker is, with everything that decides what can happen from there.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct State {
+    pc: usize,
+    pos: usize,
+    captures: Vec<Option<usize>>,
+    marks: Vec<bool>,
+}
+
+/// Iterator returned by `Regex::find_iter`.
+pub struct Matches<'r, 't> {
+    regex: &'r Regex,
+    text: &'t [u8],
+    pos: usize,
+}
+
+impl<'r, 't> Iterator for Matches<'r, 't> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        while self.pos <= self.text.len() {
+            let (s, e) = self.regex.find_at(self.text, self.pos)?;
+            if e > s {
+                self.pos = e;
+                return Some((s, e));
+            }
+            // skip over empty matches one character at a time
+            self.pos = if s < self.text.len() { s + decode(self.text, s).1 } else { s + 1 };
+        }
+        None
+    }
+}
+
+/// A sparse set of threads for the Pike VM, which keeps insertion order.
+struct Threads {
+    dense: Vec<(usize, usize)>,
+    sparse: Vec<usize>,
+}
+
+impl Threads {
+    fn new(size: usize) -> Threads {
+        Threads {
+            dense: Vec::with_capacity(size),
+            sparse: vec![0; size],
+        }
+    }
+
+    fn contains(&self, pc: usize) -> bool {
+        let i = self.sparse[pc];
+        i < self.dense.len() && self.dense[i].0 == pc
+    }
+
+    fn insert(&mut self, pc: usize, from: usize) {
+        self.sparse[pc] = self.dense.len();
+        self.dense.push((pc, from));
+    }
+
+    fn clear(&mut self) {
+        self.dense.clear();
+    }
+}
+
+fn check_look(look: Look, text: &[u8], pos: usize) -> bool {
+    match look {
+        Look::LineStart => pos == 0,
+        Look::LineEnd => pos == text.len(),
+        _ => {
+            let before = pos > 0 && is_word(decode_last(text, pos));
+            let after = pos < text.len() && is_word(decode(text, pos).0);
+            match look {
+                Look::WordBoundary => before != after,
+                Look::NotWordBoundary => before == after,
+                Look::WordStart => !before && after,
+                Look::WordEnd => before && !after,
+                Look::NotAfterWord => !before,
+                Look::NotBeforeWord => !after,
+                _ => unreachable!(),
+            }
+        },
+    }
+}
+
+fn is_word(c: Option<char>) -> bool {
+    match c {
+        Some(c) => c.is_alphanumeric() || c == '_',
+        None => false,
+    }
+}
+
+fn lower(c: char) -> char {
+    if c.is_ascii() {
+        return c.to_ascii_lowercase();
+    }
+    let mut l = c.to_lowercase();
+    match (l.next(), l.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+fn upper(c: char) -> char {
+    if c.is_ascii() {
+        return c.to_ascii_uppercase();
+    }
+    let mut u = c.to_uppercase();
+    match (u.next(), u.next()) {
+        (Some(u), None) => u,
+        _ => c,
+    }
+}
+
+/// Decodes the character starting at byte *pos* of *text*, returning it along with its length.
+/// A byte that does not begin a valid UTF-8 sequence decodes to None, with a length of one.
+pub fn decode(text: &[u8], pos: usize) -> (Option<char>, usize) {
+    let b = text[pos];
+    let width = match b {
+        0x00..=0x7f => return (Some(b as char), 1),
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => return (None, 1),
+    };
+    if pos + width > text.len() {
+        return (None, 1);
+    }
+    match str::from_utf8(&text[pos..pos + width]) {
+        Ok(s) => (s.chars().next(), width),
+        Err(_) => (None, 1),
+    }
+}
+
+/// Is *pos* the start of a character in *text*, and not inside one?
+fn is_boundary(text: &[u8], pos: usize) -> bool {
+    (1..=3).all(|w| w > pos || decode(text, pos - w).1 <= w)
+}
+
+/// Decodes the character that ends just before byte *pos* of *text*, if it is valid UTF-8.
+fn decode_last(text: &[u8], pos: usize) -> Option<char> {
+    for width in 1..=4 {
+        if width > pos {
+            break;
+        }
+        if let (Some(c), len) = decode(text, pos - width) {
+            if len == width {
+                return Some(c);
+            }
+        }
+    }
+    None
+}
+
+/// Iterates over the offsets where a character starts in a text, including the end of the text.
+struct Boundaries<'t> {
+    text: &'t [u8],
+    pos: Option<usize>,
+}
+
+impl<'t> Boundaries<'t> {
+    fn new(text: &'t [u8], start: usize) -> Boundaries<'t> {
+        Boundaries { text, pos: Some(start) }
+    }
+}
+
+impl<'t> Iterator for Boundaries<'t> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let pos = self.pos?;
+        self.pos = if pos < self.text.len() { Some(pos + decode(self.text, pos).1) } else { None };
+        Some(pos)
+    }
+}
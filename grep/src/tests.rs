@@ -0,0 +1,610 @@
+/*
+ * grep/tests.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+extern crate assert_cli;
+
+use crate::color::parse_colors;
+use crate::{compile, grep, BinaryFiles, Config, Options, OutputState, Palette, Regex, Syntax};
+use assert_cli::Assert;
+use std::process;
+
+fn matches(pattern: &str, syntax: Syntax, text: &str) -> bool {
+    Regex::new(pattern, syntax).unwrap().is_match(text.as_bytes())
+}
+
+fn find(pattern: &str, syntax: Syntax, text: &str) -> Option<(usize, usize)> {
+    Regex::new(pattern, syntax).unwrap().find_at(text.as_bytes(), 0)
+}
+
+#[test]
+fn t_basic_syntax() {
+    assert!(matches("hello", Syntax::Basic, "say hello world"));
+    assert!(matches("^say", Syntax::Basic, "say hello"));
+    assert!(!matches("^hello", Syntax::Basic, "say hello"));
+    assert!(matches("world$", Syntax::Basic, "hello world"));
+    assert!(matches("h.llo", Syntax::Basic, "hallo"));
+    assert!(matches("ab*c", Syntax::Basic, "ac"));
+    assert!(matches("a\\{2,3\\}", Syntax::Basic, "baab"));
+    assert!(!matches("^a\\{2,3\\}$", Syntax::Basic, "aaaa"));
+    // these are ordinary characters in basic syntax
+    assert!(matches("a+b", Syntax::Basic, "a+b"));
+    assert!(matches("(x|y)", Syntax::Basic, "(x|y)"));
+    assert!(matches("*a", Syntax::Basic, "*a"));
+    assert!(matches("a^b$c", Syntax::Basic, "a^b$c"));
+    // GNU extensions
+    assert!(matches("ab\\+c", Syntax::Basic, "abbc"));
+    assert!(matches("cat\\|dog", Syntax::Basic, "hotdog"));
+}
+
+#[test]
+fn t_extended_syntax() {
+    assert!(matches("fn (main|grep)", Syntax::Extended, "fn grep()"));
+    assert!(!matches("fn (main|grep)", Syntax::Extended, "fn cat()"));
+    assert!(matches("^(ab)+$", Syntax::Extended, "ababab"));
+    assert!(!matches("^(ab)+$", Syntax::Extended, "ababa"));
+    assert!(matches("colou?r", Syntax::Extended, "color"));
+    assert!(matches("x{2}", Syntax::Extended, "axxb"));
+    assert!(matches("a{,", Syntax::Extended, "a{,"));
+    assert!(matches("a)", Syntax::Extended, "a)"));
+}
+
+#[test]
+fn t_fixed_syntax() {
+    assert!(matches("a.c[", Syntax::Fixed, "xa.c[x"));
+    assert!(!matches("a.c", Syntax::Fixed, "abc"));
+    assert!(matches("", Syntax::Fixed, "anything"));
+}
+
+#[test]
+fn t_bracket_expressions() {
+    assert!(matches("[[:digit:]][[:alpha:]]", Syntax::Basic, "x9z"));
+    assert!(matches("^[^[:space:]]*$", Syntax::Basic, "nospaces"));
+    assert!(!matches("^[^[:space:]]*$", Syntax::Basic, "has spaces"));
+    assert!(matches("[]a]", Syntax::Basic, "]"));
+    assert!(matches("[a-]", Syntax::Basic, "-"));
+    assert!(matches("[\\]", Syntax::Basic, "\\"));
+    assert!(matches("[é-ë]", Syntax::Basic, "caféine"));
+    assert!(Regex::new("[b-a]", Syntax::Basic).is_err());
+    assert!(Regex::new("[[:nope:]]", Syntax::Basic).is_err());
+    assert!(Regex::new("[:space:]", Syntax::Basic).is_err());
+    assert!(Regex::new("[abc", Syntax::Basic).is_err());
+}
+
+#[test]
+fn t_back_references() {
+    assert!(matches("\\(ab\\)\\1", Syntax::Basic, "xababx"));
+    assert!(!matches("\\(ab\\)\\1", Syntax::Basic, "xabx"));
+    assert!(matches("^\\(.*\\)-\\1$", Syntax::Basic, "foo-foo"));
+    assert!(!matches("^\\(.*\\)-\\1$", Syntax::Basic, "foo-bar"));
+    assert!(matches("(a|b)\\1", Syntax::Extended, "abba"));
+    assert_eq!(Regex::new("\\(a\\1\\)", Syntax::Basic).unwrap_err().to_string(), "Invalid back reference");
+    assert!(Regex::new("\\2\\(a\\)", Syntax::Basic).is_err());
+    // a group from another branch is never set
+    assert_eq!(Regex::new("(a)|\\1x", Syntax::Extended).unwrap_err().to_string(), "Invalid back reference");
+    assert!(matches("((a)|b)\\2", Syntax::Extended, "aa"));
+    // an empty iteration still sets the group
+    assert_eq!(find("\\(a*\\)*\\1b", Syntax::Basic, "cb"), Some((1, 2)));
+}
+
+#[test]
+fn t_backtracking_blowup() {
+    let many = "a".repeat(40);
+    assert!(!matches("\\(a*\\)*\\1b", Syntax::Basic, &many));
+    assert_eq!(find("\\(a*\\)*\\1b", Syntax::Basic, &(many.clone() + "cb")), Some((41, 42)));
+    assert!(matches("\\(a*\\)*\\1b", Syntax::Basic, &(many + "b")));
+
+    // lines that can't match are ruled out without backtracking through every way to split
+    // up the a's, and the others are only backtracked from where a match could start
+    let many = "a".repeat(3000);
+    assert!(!matches("(a+)+\\1d", Syntax::Extended, &many));
+    assert_eq!(find("(a+)+\\1d", Syntax::Extended, &(many.clone() + "c aad")), Some((3002, 3005)));
+    // the group's anchor is where it matched, not where the back-reference is
+    assert!(matches("\\(^a\\)\\1", Syntax::Basic, "aa"));
+    assert!(matches("\\(\\<a\\)\\1", Syntax::Basic, "aa"));
+}
+
+#[test]
+fn t_leftmost_longest() {
+    assert_eq!(find("a|ab", Syntax::Extended, "xabc"), Some((1, 3)));
+    assert_eq!(find("b*", Syntax::Basic, "abb"), Some((0, 0)));
+    assert_eq!(find("(a|ab)(c|bcd)", Syntax::Extended, "abcd"), Some((0, 4)));
+    assert_eq!(find("\\(a*\\)b\\1", Syntax::Basic, "xaabaa"), Some((1, 6)));
+    let r = Regex::new("o+", Syntax::Extended).unwrap();
+    let all: Vec<_> = r.find_iter(b"foo boo").collect();
+    assert_eq!(all, vec![(1, 3), (5, 7)]);
+}
+
+#[test]
+fn t_word_assertions() {
+    assert!(matches("\\<cat\\>", Syntax::Basic, "a cat sat"));
+    assert!(!matches("\\<cat\\>", Syntax::Basic, "concatenate"));
+    assert!(matches("\\bis\\b", Syntax::Extended, "this is"));
+    assert!(matches("\\w\\W\\w", Syntax::Basic, "a-b"));
+}
+
+#[test]
+fn t_syntax_errors() {
+    assert_eq!(Regex::new("\\(a", Syntax::Basic).unwrap_err().to_string(), "Unmatched ( or \\(");
+    assert_eq!(Regex::new("a\\)", Syntax::Basic).unwrap_err().to_string(), "Unmatched ) or \\)");
+    assert_eq!(Regex::new("(a", Syntax::Extended).unwrap_err().to_string(), "Unmatched ( or \\(");
+    assert_eq!(Regex::new("a\\", Syntax::Basic).unwrap_err().to_string(), "Trailing backslash");
+    assert!(Regex::new("a\\{3,1\\}", Syntax::Basic).is_err());
+}
+
+#[test]
+fn t_nested_empty_loops() {
+    assert!(matches("^\\(a*\\)*$", Syntax::Basic, "aaa"));
+    assert!(matches("^(a*)*b$", Syntax::Extended, "aab"));
+    assert!(!matches("^(a|b*)*c$", Syntax::Extended, "abab"));
+}
+
+fn run(pattern: &str, input: &str, options: &Options) -> (u64, String) {
+    let regex = compile(&[pattern], options).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    let mut state: OutputState = Default::default();
+    let count = grep(&regex, "input", input.as_bytes(), &mut out, options, &mut state).unwrap();
+    (count, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn t_grep_lines() {
+    let (count, out) = run("o.e", "one\ntwo\nmore\nnone\n", &Default::default());
+    assert_eq!(count, 3);
+    assert_eq!(out, "one\nmore\nnone\n");
+}
+
+#[test]
+fn t_options() {
+    let input = "Apple pie\nbanana\napple tart\ncherry\n";
+
+    let o = Options { ignore_case: true, line_number: true, ..Default::default() };
+    assert_eq!(run("apple", input, &o).1, "1:Apple pie\n3:apple tart\n");
+
+    let o = Options { invert: true, count: true, with_filename: true, ..Default::default() };
+    assert_eq!(run("apple", input, &o), (3, "input:3\n".to_string()));
+
+    let o = Options { only_matching: true, syntax: Syntax::Extended, ..Default::default() };
+    assert_eq!(run("an+", input, &o).1, "an\nan\n");
+
+    let o = Options { max_count: Some(1), ..Default::default() };
+    assert_eq!(run("e", input, &o).1, "Apple pie\n");
+
+    let o = Options { max_count: Some(0), count: true, ..Default::default() };
+    assert_eq!(run("a", input, &o), (0, "0\n".to_string()));
+
+    let o = Options { files_with_matches: true, ..Default::default() };
+    assert_eq!(run("tart", input, &o), (1, "input\n".to_string()));
+    assert_eq!(run("plum", input, &o), (0, "".to_string()));
+
+    let o = Options { files_without_match: true, ..Default::default() };
+    assert_eq!(run("plum", input, &o), (0, "input\n".to_string()));
+
+    let o = Options { quiet: true, ..Default::default() };
+    assert_eq!(run("a", input, &o), (1, "".to_string()));
+}
+
+#[test]
+fn t_multiple_patterns() {
+    let config = Config { syntax: Syntax::Extended, ..Default::default() };
+    let regex = Regex::with_patterns(&["cat", "d(o)g", "(b)\\1"], &config).unwrap();
+    assert!(regex.is_match(b"hotdog"));
+    assert!(regex.is_match(b"abba"));
+    assert!(!regex.is_match(b"bob"));
+    assert_eq!(regex.find_at(b"a catalog", 0), Some((2, 5)));
+
+    // the longest of the strings starting leftmost wins, whichever order they came in
+    let config = Config { syntax: Syntax::Fixed, ignore_case: true, ..Default::default() };
+    let regex = Regex::with_patterns(&["ab", "ABCD", "b", "x.y"], &config).unwrap();
+    assert_eq!(regex.find_iter(b"zabcdab x.y xzy").collect::<Vec<_>>(), vec![(1, 5), (5, 7), (8, 11)]);
+    assert!(!Regex::with_patterns::<&str>(&[], &config).unwrap().is_match(b"anything"));
+
+    let o = Options { only_matching: true, ..Default::default() };
+    let regex = compile(&["plum", "app\\|pie\ntart"], &o).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    let input = "Apple pie\nplum tart\n";
+    grep(&regex, "input", input.as_bytes(), &mut out, &o, &mut Default::default()).unwrap();
+    assert_eq!(out, b"pie\nplum\ntart\n".to_vec());
+}
+
+#[test]
+fn t_many_patterns() {
+    // patterns that aren't all strings are each run only where the string they need is. Run
+    // through one program together, these took minutes.
+    let mut patterns: Vec<String> = (0..2000).map(|i| format!("w{}x.", i)).collect();
+    patterns.push(String::from("^q*$"));
+    patterns.push(String::from("(ab)\\1"));
+    let config = Config { syntax: Syntax::Extended, ..Default::default() };
+    let regex = Regex::with_patterns(&patterns, &config).unwrap();
+    let text = "nothing much to see on this line, or the next one\n".repeat(10_000);
+    let start = std::time::Instant::now();
+    assert_eq!(text.lines().filter(|l| regex.is_match(l.as_bytes())).count(), 0);
+    assert!(start.elapsed().as_secs() < 10);
+
+    assert!(regex.is_match(b"a w1999xy"));
+    assert!(!regex.is_match(b"a w1999x"));
+    assert!(regex.is_match(b"qqq"));
+    assert!(regex.is_match(b"abab"));
+    // the leftmost match of any of them, and the longest there
+    assert_eq!(regex.find_at(b"w12 w12xy w1xz", 0), Some((4, 9)));
+    assert_eq!(regex.find_at(b"w12 w12xy w1xz", 5), Some((10, 14)));
+    let config = Config { word: true, ..config };
+    let regex = Regex::with_patterns(&patterns, &config).unwrap();
+    assert!(!regex.is_match(b"w12x3z"));
+    assert!(regex.is_match(b"w12x3z w12x!"));
+}
+
+#[test]
+fn t_pattern_files_cli() {
+    Assert::main_binary()
+        .stdin("version\nedition\n")
+        .with_args(&["-F", "-f", "-", "-e", "name", "Cargo.toml"])
+        .stdout().is("name = \"grep\"\nversion = \"0.1.0\"\nedition = \"2018\"")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-f", "no-such-file", "Cargo.toml"])
+        .fails_with(2)
+        .stderr().contains("grep: no-such-file: ")
+        .unwrap();
+}
+
+#[test]
+fn t_word_and_line() {
+    let input = "foo bar\nfoobar\nbar foo_x foo\n@foo a@foo\nfoo\nfoo-foo\n";
+
+    let o = Options { word_regexp: true, line_number: true, ..Default::default() };
+    assert_eq!(run("foo", input, &o), (5, "1:foo bar\n3:bar foo_x foo\n4:@foo a@foo\n5:foo\n6:foo-foo\n".to_string()));
+    // there has to be something other than a word character on both sides, not just a boundary
+    assert_eq!(run("@foo", input, &o).1, "4:@foo a@foo\n");
+    assert_eq!(run("foo.", input, &o).1, "");
+    assert_eq!(run("foo_x", input, &o).1, "3:bar foo_x foo\n");
+
+    let o = Options { word_regexp: true, only_matching: true, byte_offset: true, ..Default::default() };
+    assert_eq!(run("foo", input, &o).1, "0:foo\n25:foo\n30:foo\n36:foo\n40:foo\n44:foo\n48:foo\n");
+
+    let o = Options { line_regexp: true, syntax: Syntax::Fixed, ..Default::default() };
+    let regex = compile(&["foo", "foobar", "bar"], &o).unwrap();
+    assert!(!regex.is_literal());
+    assert!(regex.is_match(b"foobar"));
+    assert!(!regex.is_match(b"foo bar"));
+    assert_eq!(run("foo\nfoo bar", input, &o).1, "foo bar\nfoo\n");
+
+    let o = Options { word_regexp: true, syntax: Syntax::Fixed, ..Default::default() };
+    let regex = compile(&["foo-bar", "foo"], &o).unwrap();
+    assert_eq!(regex.find_at(b"foo-bar foo", 0), Some((0, 7)));
+    // a longer string that doesn't fit can hide a shorter one that does, where it starts or later
+    assert_eq!(regex.find_at(b"foo-barx", 0), Some((0, 3)));
+    assert_eq!(regex.find_at(b"xfoo-bar foo", 0), Some((9, 12)));
+    assert_eq!(compile(&["", "zz"], &o).unwrap().find_at(b"-", 0), Some((0, 0)));
+    assert_eq!(compile(&["", "zz"], &Default::default()).unwrap().find_at(b"-", 0), Some((0, 0)));
+
+    let o = Options { byte_offset: true, before_context: 1, ..Default::default() };
+    assert_eq!(run("^foo$", input, &o).1, "29-@foo a@foo\n40:foo\n");
+}
+
+#[test]
+fn t_label_cli() {
+    Assert::main_binary()
+        .stdin("one\ntwo\nthree\n")
+        .with_args(&["-Hnb", "--label=numbers", "-x", "-e", "two", "-e", "three", "-", "Cargo.toml"])
+        .stdout().is("numbers:2:4:two\nnumbers:3:8:three")
+        .unwrap();
+}
+
+#[test]
+fn t_extended_cli() {
+    Assert::main_binary()
+        .with_args(&["-E", "^name = \"(grep|cat)\"$", "Cargo.toml"])
+        .stdout().is("name = \"grep\"")
+        .unwrap();
+}
+
+#[test]
+fn t_context() {
+    let input = "1\n2\nmatch a\n4\n5\n6\n7\nmatch b\nmatch c\n10\n11\n";
+
+    let o = Options { before_context: 1, after_context: 1, line_number: true, ..Default::default() };
+    assert_eq!(run("match", input, &o).1, "2-2\n3:match a\n4-4\n--\n7-7\n8:match b\n9:match c\n10-10\n");
+
+    // overlapping windows merge into one group
+    let o = Options { before_context: 3, ..Default::default() };
+    assert_eq!(run("match", input, &o).1, "1\n2\nmatch a\n--\n5\n6\n7\nmatch b\nmatch c\n");
+
+    let o = Options { after_context: 2, group_separator: None, with_filename: true, ..Default::default() };
+    assert_eq!(run("match a\\|11", input, &o).1, "input:match a\ninput-4\ninput-5\ninput:11\n");
+
+    // trailing context is still printed after the last line -m allows
+    let o = Options { after_context: 1, max_count: Some(1), ..Default::default() };
+    assert_eq!(run("match", input, &o), (1, "match a\n4\n".to_string()));
+
+    let o = Options { after_context: 1, invert: true, ..Default::default() };
+    assert_eq!(run("[0-9]", input, &o).1, "match a\n4\n--\nmatch b\nmatch c\n10\n");
+}
+
+#[test]
+fn t_binary_files() {
+    let input = "text line\nbinary\0line\n";
+    assert_eq!(run("line", input, &Default::default()).1, "Binary file input matches\n");
+
+    let o = Options { count: true, ..Default::default() };
+    assert_eq!(run("line", input, &o).1, "2\n");
+
+    let o = Options { binary_files: BinaryFiles::WithoutMatch, ..Default::default() };
+    assert_eq!(run("line", input, &o), (0, "".to_string()));
+
+    let o = Options { binary_files: BinaryFiles::Text, ..Default::default() };
+    assert_eq!(run("text", input, &o).1, "text line\n");
+}
+
+#[test]
+fn t_whole_buffers() {
+    // a few megabytes, with lines that cross from one buffer into the next, and one longer than
+    // a whole buffer
+    let mut input = Vec::new();
+    for i in 0..120_000 {
+        input.extend_from_slice(format!("line {} {}\n", i, if i % 7 == 0 { "needle" } else { "hay" }).as_bytes());
+    }
+    input.extend(std::iter::repeat_n(b'x', 3 << 19));
+    input.extend_from_slice(b"needle\nbinary\0needle\nneedle without a newline");
+
+    // the second regex isn't a plain string, so it is searched for line by line
+    let search = |pattern: &str, options: &Options| {
+        let regex = compile(&[pattern], options).unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        let count = grep(&regex, "input", &input[..], &mut out, options, &mut Default::default()).unwrap();
+        (count, out)
+    };
+    for o in [
+        Options { line_number: true, ..Default::default() },
+        Options { line_number: true, binary_files: BinaryFiles::Text, ..Default::default() },
+        Options { only_matching: true, byte_offset: true, binary_files: BinaryFiles::Text, ..Default::default() },
+        Options { byte_offset: true, ..Default::default() },
+        Options { count: true, ..Default::default() },
+        Options { max_count: Some(20_000), line_number: true, ..Default::default() },
+    ].iter() {
+        assert!(compile(&["needle"], o).unwrap().is_literal());
+        assert!(search("needle", o) == search("needl[e]", o));
+    }
+
+    let o = Options { line_number: true, binary_files: BinaryFiles::Text, ..Default::default() };
+    let (count, out) = search("needle", &o);
+    assert_eq!(count, 17_146);
+    assert!(out.ends_with(b"120002:binary\0needle\n120003:needle without a newline\n"));
+}
+
+#[test]
+fn t_recursive_cli() {
+    Assert::main_binary()
+        .with_args(&["-r", "--include=*.toml", "--exclude-dir=target", "^name", "."])
+        .stdout().is("./Cargo.toml:name = \"grep\"")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-l", "--exclude=*.rs", "-r", "EXIT_TROUBLE", "src"])
+        .fails_with(1)
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["name", "src"])
+        .fails_with(2)
+        .stderr().is("grep: src: Is a directory")
+        .unwrap();
+}
+
+#[test]
+fn t_raw_bytes() {
+    let regex = compile(&["caf."], &Default::default()).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    let mut state: OutputState = Default::default();
+
+    // Latin-1 text is not valid UTF-8, and comes out exactly as it went in
+    let input: &[u8] = b"caf\xe9 au lait\nthe\xff\xfe cafe\nno final newline caff";
+    let count = grep(&regex, "input", input, &mut out, &Default::default(), &mut state).unwrap();
+    assert_eq!(count, 3);
+    assert_eq!(out, b"caf\xe9 au lait\nthe\xff\xfe cafe\nno final newline caff\n".to_vec());
+
+    let o = Options { null_data: true, ..Default::default() };
+    out.clear();
+    let input: &[u8] = b"one\ncafe\0two\0\xe9caf\xe9";
+    let count = grep(&regex, "input", input, &mut out, &o, &mut state).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(out, b"one\ncafe\0\xe9caf\xe9\0".to_vec());
+
+    // a byte that isn't UTF-8 is one character, which no literal matches
+    let regex = compile(&["a.b"], &Default::default()).unwrap();
+    assert!(regex.is_match(b"xa\xe9b"));
+    assert!(!regex.is_match(b"xa\xe9\xe9b"));
+    assert!(!compile(&["a[a-z]b"], &Default::default()).unwrap().is_match(b"xa\xe9b"));
+    assert!(compile(&["a[^a-z]b"], &Default::default()).unwrap().is_match(b"xa\xe9b"));
+
+    // patterns can have them too, and there such a byte only matches itself
+    let regex = compile(&[&b"caf\xe9"[..]], &Default::default()).unwrap();
+    assert!(regex.is_match(b"un caf\xe9"));
+    assert!(!regex.is_match("un caf\u{e9}".as_bytes()));
+    // \xe9\xa9\xa9 is a character, so the \xe9 in it isn't a byte on its own
+    assert!(!regex.is_match(b"un caf\xe9\xa9\xa9"));
+    let o = Options { syntax: Syntax::Fixed, ..Default::default() };
+    assert!(compile(&[&b"\xe9t\xe9"[..]], &o).unwrap().is_match(b"l'\xe9t\xe9"));
+    assert!(compile(&[&b"[\xe8\xe9]t"[..]], &Default::default()).unwrap().is_match(b"\xe9t"));
+    assert!(!compile(&[&b"[^\xe8\xe9]t"[..]], &Default::default()).unwrap().is_match(b"\xe9t"));
+}
+
+#[test]
+fn t_raw_bytes_cli() {
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+
+    Assert::main_binary()
+        .with_args(&[OsStr::from_bytes(b"caf\xe9")])
+        .stdin(&b"caf\xe9\ncafe\n"[..])
+        .stdout().is(&b"caf\xe9\n"[..])
+        .unwrap();
+    let dir = std::env::temp_dir().join(format!("grep-raw-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let name = dir.join(OsStr::from_bytes(b"\xe9t\xe9"));
+    fs::write(&name, "summer\n").unwrap();
+    Assert::main_binary()
+        .with_args(&[OsStr::new("-c"), OsStr::new("summer"), name.as_os_str()])
+        .stdout().is("1")
+        .unwrap();
+    // patterns read from a file are bytes as well
+    let patterns = dir.join("patterns");
+    fs::write(&patterns, b"caf\xe9\nth\xe9\n").unwrap();
+    Assert::main_binary()
+        .with_args(&[OsStr::new("-f"), patterns.as_os_str()])
+        .stdin(&b"caf\xe9\ncafe\nth\xe9 noir\n"[..])
+        .stdout().is(&b"caf\xe9\nth\xe9 noir\n"[..])
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn t_context_cli() {
+    Assert::main_binary()
+        .with_args(&["-C", "1", "--group-separator=..", "^version\\|^clap", "Cargo.toml", "Cargo.toml"])
+        .stdout().contains("Cargo.toml-name = \"grep\"\nCargo.toml:version = \"0.1.0\"\nCargo.toml-authors")
+        .stdout().contains("\n..\nCargo.toml-name = \"grep\"\n")
+        .unwrap();    // no lines of context, but still a separator between groups
+    Assert::main_binary()
+        .with_args(&["-A0", "--group-separator=XX", "x"])
+        .stdin("x\ny\nx\nx\n")
+        .stdout().is("x\nXX\nx\nx")
+        .unwrap();
+}
+
+#[test]
+fn t_parallel_cli() {
+    let args = ["-r", "-n", "-C", "1", "--exclude=*.lock", "--exclude-dir=target", "Errors\\|^use", "src", "Cargo.toml", "no-such-file"];
+    let serial = process::Command::new("cargo").args(["run", "--quiet", "--", "--threads=1"]).args(args).output().unwrap();
+    assert_eq!(serial.status.code(), Some(2));
+    assert!(serial.stdout.starts_with(b"src/bin/main.rs-"));
+
+    // the workers finish in any order, but the output is the same as searching one at a time
+    let mut parallel = Assert::main_binary()
+        .with_args(&["--threads=4"])
+        .with_args(&args)
+        .fails_with(2)
+        .stderr().is("grep: no-such-file: No such file or directory (os error 2)");
+    parallel = parallel.stdout().is(&String::from_utf8(serial.stdout).unwrap()[..]);
+    parallel.unwrap();
+}
+
+#[test]
+fn t_parallel_held_output() {
+    use std::ffi::OsStr;
+    use std::fs;
+
+    // more output than a worker holds on to, and standard input, are searched in their turn
+    let dir = std::env::temp_dir().join(format!("grep-held-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let big: String = (0..40000).map(|i| format!("line {} of a long file\n", i)).collect();
+    fs::write(dir.join("big"), &big).unwrap();
+    fs::write(dir.join("small"), "one line\nand another\n").unwrap();
+    let (big, small) = (dir.join("big"), dir.join("small"));
+    let args = [OsStr::new("-n"), OsStr::new("-C"), OsStr::new("1"), OsStr::new("line"),
+        small.as_os_str(), big.as_os_str(), OsStr::new("-"), small.as_os_str(), big.as_os_str()];
+    let serial = process::Command::new("cargo")
+        .args(["run", "--quiet", "--", "--threads=1"])
+        .args(args)
+        .stdin(process::Stdio::null())
+        .output()
+        .unwrap();
+    assert_eq!(serial.status.code(), Some(0));
+    assert!(serial.stdout.len() > 2 * 1024 * 1024);
+
+    Assert::main_binary()
+        .with_args(&[OsStr::new("--threads=4")])
+        .with_args(&args)
+        .stdout().is(&serial.stdout[..])
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn t_exit_status() {
+    Assert::main_binary()
+        .with_args(&["-q", "no such line", "Cargo.toml"])
+        .fails_with(1)
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-L", "name", "Cargo.toml"])
+        .stdout().is("")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-L", "no such line", "Cargo.toml"])
+        .fails_with(1)
+        .stdout().is("Cargo.toml")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-s", "name", "Cargo.toml", "no-such-file"])
+        .fails_with(2)
+        .stdout().contains("Cargo.toml:name = \"grep\"")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-qs", "name", "no-such-file", "Cargo.toml"])
+        .succeeds()
+        .stdout().is("")
+        .unwrap();
+    // mistakes in the arguments are trouble too, but asking for help isn't
+    for args in &[&["--no-such-option", "name"][..], &[], &["--binary-files=maybe", "name"]] {
+        Assert::main_binary()
+            .with_args(args)
+            .fails_with(2)
+            .stderr().contains("error:")
+            .unwrap();
+    }
+    Assert::main_binary()
+        .with_args(&["--help"])
+        .succeeds()
+        .stdout().contains("USAGE:")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["--version"])
+        .succeeds()
+        .stdout().contains("grep")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-E", "a(", "Cargo.toml"])
+        .fails_with(2)
+        .stderr().is("grep: Unmatched ( or \\(")
+        .unwrap();
+}
+
+#[test]
+fn t_colors() {
+    let map = parse_colors("ms=04;32:ln=1:rv:bogus:fn=red:mt");
+    assert_eq!(map.get("ms"), Some(&"04;32"));
+    assert_eq!(map.get("mc"), Some(&"01;31"));
+    assert_eq!(map.get("ln"), Some(&"1"));
+    assert_eq!(map.get("fn"), Some(&"35"));
+    assert_eq!(map.get("bogus"), None);
+
+    let palette = Palette::new(&map);
+    assert!(palette.flag("rv"));
+    assert!(!palette.flag("ne"));
+    let mut out: Vec<u8> = Vec::new();
+    palette.paint(&mut out, "ms", b"hi").unwrap();
+    palette.paint(&mut out, "sl", b"plain").unwrap();
+    assert_eq!(out, b"\x1b[04;32m\x1b[Khi\x1b[m\x1b[Kplain".to_vec());
+
+    let mt = parse_colors("mt=7:ne");
+    assert_eq!(mt.get("mc"), Some(&"7"));
+    out.clear();
+    Palette::new(&mt).paint(&mut out, "mc", b"x").unwrap();
+    assert_eq!(out, b"\x1b[7mx\x1b[m".to_vec());
+}
+
+#[test]
+fn t_color_cli() {
+    Assert::main_binary()
+        .with_env(assert_cli::Environment::inherit().insert("GREP_COLORS", "ms=4:fn=:se="))
+        .with_args(&["--color=always", "-H", "^name", "Cargo.toml"])
+        .stdout().is("Cargo.toml:\x1b[4m\x1b[Kname\x1b[m\x1b[K = \"grep\"")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["--color=never", "^name", "Cargo.toml"])
+        .stdout().is("name = \"grep\"")
+        .unwrap();
+}
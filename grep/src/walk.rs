@@ -0,0 +1,142 @@
+/*
+ * grep/walk.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+extern crate glob;
+extern crate walkdir;
+
+use glob::Pattern;
+use walkdir::WalkDir;
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::{Errors, GrepResult, Options};
+
+/// Something to search: a path, or "-" for standard input, and the name to print for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Input {
+    pub path: PathBuf,
+    pub name: String,
+}
+
+impl Input {
+    /// Standard input.
+    pub fn stdin() -> Input {
+        Input {
+            path: PathBuf::from("-"),
+            name: String::from("(standard input)"),
+        }
+    }
+
+    fn file(path: &Path) -> Input {
+        Input {
+            path: path.to_path_buf(),
+            name: path.to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Is this standard input rather than a file?
+    pub fn is_stdin(&self) -> bool {
+        self.path.as_os_str() == "-"
+    }
+}
+
+fn matches_any(patterns: &[Pattern], name: &OsStr) -> bool {
+    let name = name.to_string_lossy();
+    patterns.iter().any(|p| p.matches(&name))
+}
+
+/// Should a file with this base name be skipped, given --include and --exclude?
+fn skip_file(name: &OsStr, options: &Options) -> bool {
+    if matches_any(&options.exclude, name) {
+        return true;
+    }
+    !options.include.is_empty() && !matches_any(&options.include, name)
+}
+
+/// Should a directory with this base name be skipped, given --exclude-dir?
+fn skip_dir(name: &OsStr, options: &Options) -> bool {
+    matches_any(&options.exclude_dir, name)
+}
+
+/// Expands the operands in *files* into the inputs to search, in order, and hands each one (or
+/// the error found while looking for it) to *visit*. Directories are walked in name order when
+/// searching recursively. With no operands, this is standard input, or the current directory
+/// for a recursive search. Stops early and returns false if *visit* returns false.
+pub fn expand<F>(files: &[&OsStr], options: &Options, mut visit: F) -> bool
+    where F: FnMut(GrepResult<Input>) -> bool
+{
+    let implicit = files.is_empty();
+    let default = [OsStr::new(if options.recursive { "." } else { "-" })];
+    let operands = if implicit { &default[..] } else { files };
+
+    for &file in operands {
+        if file == "-" {
+            let mut input = Input::stdin();
+            if let Some(ref label) = options.label {
+                input.name = label.clone();
+            }
+            if !visit(Ok(input)) {
+                return false;
+            }
+            continue;
+        }
+
+        let path = Path::new(file);
+        let base = path.file_name().unwrap_or(path.as_os_str());
+        if !fs::metadata(path).map(|m| m.is_dir()).unwrap_or(false) {
+            if !skip_file(base, options) && !visit(Ok(Input::file(path))) {
+                return false;
+            }
+            continue;
+        }
+
+        if !options.recursive {
+            if !visit(Err(Errors::Directory(file.to_string_lossy().into_owned()))) {
+                return false;
+            }
+            continue;
+        }
+        if file != "." && skip_dir(base, options) {
+            continue;
+        }
+
+        let walker = WalkDir::new(path)
+            .follow_links(options.dereference_recursive)
+            .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_type().is_dir() || !skip_dir(e.file_name(), options));
+
+        for entry in walker {
+            let found = match entry {
+                Ok(e) => {
+                    // symbolic links met along the way are only followed with -R, and devices
+                    // and pipes are never read while recursing
+                    if !e.file_type().is_file() || skip_file(e.file_name(), options) {
+                        continue;
+                    }
+                    let mut input = Input::file(e.path());
+                    if implicit {
+                        if let Ok(rest) = e.path().strip_prefix(".") {
+                            input.name = rest.to_string_lossy().into_owned();
+                        }
+                    }
+                    Ok(input)
+                },
+                Err(e) => {
+                    let name = e.path().unwrap_or(path).to_string_lossy().into_owned();
+                    Err(Errors::Input(io::Error::from(e), name))
+                }
+            };
+            if !visit(found) {
+                return false;
+            }
+        }
+    }
+    true
+}
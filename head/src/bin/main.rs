@@ -0,0 +1,139 @@
+/*
+ * head/main.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+extern crate clap;
+extern crate head;
+
+use clap::{Arg, App};
+use head::*;
+use std::{env, io, process};
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter, stdin, stdout, stderr};
+use std::fs::File;
+
+fn main() -> io::Result<()> {
+    // GNU's obsolete -NUM[bkm][cl][qv], as in head -5 or head -2kc
+    let takes = cli::Obsolete { plus: false, multipliers: "bkm", flags: "qv" };
+    let args = cli::obsolete(env::args().collect(), &takes);
+    let matches = App::new("head").version(env!("CARGO_PKG_VERSION"))
+        .about("Print the first 10 lines of each FILE to standard output\nReads from stdin if FILE is -, or if there is no FILE")
+        .after_help("NUM may have a multiplier suffix: b 512, kB 1000, K 1024, MB 1000*1000, M 1024*1024,\n\
+                     and so on for G, T, P, E, Z and Y. KiB, MiB and so on are the same as K, M.\n\
+                     -NUM on its own is the same as -n NUM.")
+        .arg(Arg::with_name("FILE")
+            .help("The file to read")
+            .required(false)
+            .index(1)
+            .multiple(true))
+        .arg(Arg::with_name("bytes")
+            .short("c")
+            .long("bytes")
+            .value_name("[-]NUM")
+            .help("print the first NUM bytes of each file, or all but the last NUM bytes with a leading '-'")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("lines")
+            .short("n")
+            .long("lines")
+            .value_name("[-]NUM")
+            .help("print the first NUM lines instead of the first 10, or all but the last NUM lines with a leading '-'")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .alias("silent")
+            .help("never print headers giving file names")
+            .multiple(true)
+            .overrides_with("verbose"))
+        .arg(Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .help("always print headers giving file names")
+            .multiple(true)
+            .overrides_with("quiet"))
+        .arg(Arg::with_name("zero-terminated")
+            .short("z")
+            .long("zero-terminated")
+            .help("line delimiter is NUL, not newline"))
+        .get_matches_from(&args);
+
+    let mut options: Options = Default::default();
+    let stdin = stdin();
+    let stderr = stderr();
+
+    match cli::count(&matches) {
+        // a leading '-' means all but the last NUMBER, which is different even for -0
+        Ok(Some(count)) => {
+            options.mode = match (count.unit, count.sign == Some('-')) {
+                (cli::Unit::Lines, false) => Mode::Lines(count.number),
+                (cli::Unit::Lines, true) => Mode::AllButLines(count.number),
+                (cli::Unit::Bytes, false) => Mode::Bytes(count.number),
+                (cli::Unit::Bytes, true) => Mode::AllButBytes(count.number)
+            };
+        },
+        Ok(None) => {},
+        Err(e) => {
+            writeln!(stderr.lock(), "head: {}", e)?;
+            process::exit(1);
+        }
+    }
+    options.headers = cli::headers(&matches);
+
+    if matches.is_present("zero-terminated") {
+        options.separator = b'\0';
+    }
+
+    let files: Vec<&str> = match matches.values_of("FILE") {
+        Some(f) => f.collect(),
+        None => vec!["-"]
+    };
+
+    let headers = options.headers.wanted(files.len());
+    let mut success = true;
+    let mut first = true;
+    let stdout = stdout();
+    let mut output = BufWriter::new(stdout.lock());
+
+    for file in &files {
+        let name = if *file == "-" { "standard input" } else { file };
+        let input: Box<dyn BufRead> = if *file == "-" {
+            Box::new(stdin.lock())
+        } else {
+            match File::open(file) {
+                Ok(f) => Box::new(BufReader::new(f)),
+                Err(e) => {
+                    output.flush()?;
+                    writeln!(stderr.lock(), "head: cannot open '{}' for reading: {}", name, cli::describe(&e))?;
+                    success = false;
+                    continue;
+                }
+            }
+        };
+
+        if headers {
+            write_header(&mut output, name, first)?;
+        }
+        first = false;
+        if let Err(e) = head(input, &mut output, &options.mode, options.separator) {
+            cli::exit_if_gone(&e);
+            output.flush()?;
+            writeln!(stderr.lock(), "head: error reading '{}': {}", name, cli::describe(&e))?;
+            success = false;
+        }
+    }
+
+    output.flush()?;
+    if !success {
+        process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,158 @@
+/*
+ * head/cli.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+//!
+//! The arguments head and tail share: counts for -n and -c, the obsolete `-NUM` form of
+//! them, and -q and -v for headers. Also how they report errors.
+//!
+use clap::ArgMatches;
+use std::{fmt, io, process};
+use crate::{parse_size, Headers, SizeError};
+
+/// What a program takes in an obsolete first argument like `-5` or `-2kc`, besides the number,
+/// `c` for bytes and `l` for lines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obsolete {
+    /// Whether it can start with `+`, as in `tail +20`
+    pub plus: bool,
+    /// The multiplier suffixes it takes out of `b`, `k` and `m`, which also mean bytes
+    pub multipliers: &'static str,
+    /// Letters that stand for options on their own, like `v` for `-v`
+    pub flags: &'static str
+}
+
+/// Rewrites an obsolete first argument, as in `head -5`, `head -2kc` or `tail +20f`, into the
+/// options it stands for. Any other arguments are returned as they are.
+pub fn obsolete(mut args: Vec<String>, takes: &Obsolete) -> Vec<String> {
+    let rewritten = match args.get(1) {
+        Some(first) if first.len() > 1 && (first.starts_with('-') || (takes.plus && first.starts_with('+')))
+                && first.as_bytes()[1].is_ascii_digit() => {
+            let rest = &first[1..];
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            // the number is a count from the end either way, unless it starts with '+'
+            let number = first[..=digits].replace('-', "");
+            let mut multiplier = "";
+            let mut option = "-n";
+            let mut flags = Vec::new();
+            for c in rest[digits..].chars() {
+                match c {
+                    'b' | 'k' | 'm' if takes.multipliers.contains(c) => {
+                        multiplier = match c {
+                            'b' => "b",
+                            'k' => "K",
+                            _ => "M"
+                        };
+                        option = "-c";
+                    },
+                    'c' => {
+                        multiplier = "";
+                        option = "-c";
+                    },
+                    'l' => option = "-n",
+                    _ if takes.flags.contains(c) => flags.push(format!("-{}", c)),
+                    _ => return args
+                }
+            }
+            let mut options = vec![option.to_string(), number + multiplier];
+            options.extend(flags);
+            options
+        },
+        _ => return args
+    };
+    args.splice(1..2, rewritten);
+    args
+}
+
+/// Which option a count was given to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    Lines,
+    Bytes
+}
+
+/// A count given to -n or -c.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Count {
+    pub unit: Unit,
+    /// The sign in front of the number, if there was one: `-` or `+`
+    pub sign: Option<char>,
+    pub number: u64
+}
+
+/// A count that couldn't be used, and what was given for it.
+#[derive(Debug, PartialEq)]
+pub struct CountError {
+    pub unit: Unit,
+    pub text: String,
+    pub error: SizeError
+}
+
+impl fmt::Display for CountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = match self.unit {
+            Unit::Lines => "lines",
+            Unit::Bytes => "bytes"
+        };
+        write!(f, "invalid number of {}: '{}'", unit, self.text)?;
+        if self.error == SizeError::Overflow {
+            write!(f, ": Value too large for defined data type")?;
+        }
+        Ok(())
+    }
+}
+
+/// Gets the count from clap's *matches*. The last of the `lines` and `bytes` arguments given is
+/// the one that counts, and the last value given to it. Returns `None` if neither was given.
+pub fn count(matches: &ArgMatches) -> Result<Option<Count>, CountError> {
+    let last = |name| matches.indices_of(name).and_then(|i| i.max());
+    let unit = match (last("lines"), last("bytes")) {
+        (Some(l), Some(b)) => if b > l { Unit::Bytes } else { Unit::Lines },
+        (Some(_), None) => Unit::Lines,
+        (None, Some(_)) => Unit::Bytes,
+        (None, None) => return Ok(None)
+    };
+    let name = if unit == Unit::Lines { "lines" } else { "bytes" };
+    let value = match matches.values_of(name).and_then(|mut v| v.next_back()) {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    let sign = value.chars().next().filter(|&c| c == '-' || c == '+');
+    // a '+' is part of the number as far as parse_size goes, and is printed with it
+    let text = value.strip_prefix('-').unwrap_or(value);
+    match parse_size(text) {
+        Ok(number) => Ok(Some(Count { unit, sign, number })),
+        Err(error) => Err(CountError { unit, text: text.to_string(), error })
+    }
+}
+
+/// Reads -q and -v from clap's *matches*. They override each other, so at most one is there.
+pub fn headers(matches: &ArgMatches) -> Headers {
+    if matches.is_present("quiet") {
+        Headers::Never
+    } else if matches.is_present("verbose") {
+        Headers::Always
+    } else {
+        Headers::Auto
+    }
+}
+
+/// Describes *error* the way GNU does, without the " (os error N)" Rust puts after it.
+pub fn describe(error: &io::Error) -> String {
+    let text = error.to_string();
+    match text.find(" (os error ") {
+        Some(end) => text[..end].to_owned(),
+        None => text
+    }
+}
+
+/// Exits if *error* means whoever was reading the output has gone away, as there is no one
+/// left to tell about it or anything else.
+pub fn exit_if_gone(error: &io::Error) {
+    if error.kind() == io::ErrorKind::BrokenPipe {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,277 @@
+/*
+ * head/lib.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+//!
+//! # **`head`**
+//!
+//! Print the first part of FILEs (or standard input).
+//!
+//! ```rust
+//! extern crate head;
+//! ```
+//!
+//! The first 10 lines of each file are printed, with a `==> name <==` header before each one
+//! when there is more than one. -n and -c choose how many lines or bytes, or with a leading
+//! '-', how many to leave off the end. The `cli` module holds the argument handling that tail
+//! shares.
+//!
+//! ```text
+//! $ head -n 3 notes.txt
+//! $ head -c -1K file.bin
+//! $ head -5 a.txt b.txt
+//! ```
+//!
+extern crate clap;
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io;
+use std::io::prelude::*;
+
+pub mod cli;
+
+/// How much of each file to print.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Lines(u64),
+    Bytes(u64),
+    /// All but the last so many lines: -n -NUMBER
+    AllButLines(u64),
+    /// All but the last so many bytes: -c -NUMBER
+    AllButBytes(u64)
+}
+
+/// Why a count given to -n or -c couldn't be used.
+#[derive(Debug, PartialEq)]
+pub enum SizeError {
+    /// It isn't a number with a known suffix
+    Invalid,
+    /// It is too big to count up to
+    Overflow
+}
+
+/// Reads a count like GNU head does: a decimal number, then an optional multiplier suffix.
+/// `b` is 512, `K`, `M`, `G`, `T`, `P`, `E`, `Z` and `Y` are powers of 1024, or of 1000 when
+/// followed by `B` like `KB`. `KiB` and so on are also powers of 1024.
+pub fn parse_size(text: &str) -> Result<u64, SizeError> {
+    let text = text.trim_start();
+    let text = text.strip_prefix('+').unwrap_or(text);
+    let digits = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    if digits == 0 {
+        return Err(SizeError::Invalid);
+    }
+    let (number, suffix) = text.split_at(digits);
+
+    let multiplier: u64 = match suffix {
+        "" => 1,
+        "b" => 512,
+        _ => {
+            let mut chars = suffix.chars();
+            let power = match chars.next() {
+                Some('K') | Some('k') => 1,
+                Some('M') | Some('m') => 2,
+                Some('G') => 3,
+                Some('T') => 4,
+                Some('P') => 5,
+                Some('E') => 6,
+                Some('Z') => 7,
+                Some('Y') => 8,
+                _ => return Err(SizeError::Invalid)
+            };
+            let base: u64 = match chars.as_str() {
+                "" | "iB" => 1024,
+                "B" => 1000,
+                _ => return Err(SizeError::Invalid)
+            };
+            base.checked_pow(power).ok_or(SizeError::Overflow)?
+        }
+    };
+    number.parse::<u64>()
+        .map_err(|_e| SizeError::Overflow)?
+        .checked_mul(multiplier)
+        .ok_or(SizeError::Overflow)
+}
+
+/// When to print a `==> name <==` header before each file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Headers {
+    /// Only when there is more than one file
+    Auto,
+    /// Never: -q, --quiet or --silent
+    Never,
+    /// Always: -v or --verbose
+    Always
+}
+
+impl Headers {
+    /// Whether there is a header before each file, when there are this many *files*.
+    pub fn wanted(self, files: usize) -> bool {
+        match self {
+            Headers::Auto => files > 1,
+            Headers::Always => true,
+            Headers::Never => false
+        }
+    }
+}
+
+pub struct Options {
+    pub mode: Mode,
+    pub headers: Headers,
+    /// What ends a line: newline, or NUL with -z
+    pub separator: u8
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            mode: Mode::Lines(10),
+            headers: Headers::Auto,
+            separator: b'\n'
+        }
+    }
+}
+
+/// Writes the `==> name <==` header, with a blank line to set it apart from the file before
+/// unless this is the *first*.
+pub fn write_header<W: Write>(output: &mut W, name: &str, first: bool) -> io::Result<()> {
+    if !first {
+        writeln!(output)?;
+    }
+    writeln!(output, "==> {} <==", name)
+}
+
+/// Writes the start of *input* to *output*, as much as *mode* asks for. Bytes are copied as
+/// they are, so binary files and a missing newline at the end come through unchanged. Lines
+/// end with *separator*.
+pub fn head<R: BufRead, W: Write>(input: R, output: &mut W, mode: &Mode, separator: u8) -> io::Result<()> {
+    match *mode {
+        Mode::AllButBytes(n) => {
+            hold_last_bytes(input, n, |bytes| output.write_all(bytes))?;
+        },
+        Mode::AllButLines(n) => {
+            hold_last_lines(input, n, separator, |line| output.write_all(line))?;
+        },
+        Mode::Bytes(n) => {
+            io::copy(&mut input.take(n), output)?;
+        },
+        Mode::Lines(n) => {
+            copy_lines(input, output, n, separator)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the first *n* lines of *input* to *output*, and leaves the rest of *input* unread.
+/// The last line is copied even if there is no *separator* at the end of it.
+pub fn copy_lines<R: BufRead, W: Write>(mut input: R, output: &mut W, n: u64, separator: u8) -> io::Result<()> {
+    let mut left = n;
+    while left > 0 {
+        let length = {
+            let buffer = input.fill_buf()?;
+            if buffer.is_empty() {
+                break;
+            }
+            let mut end = 0;
+            while left > 0 {
+                match buffer[end..].iter().position(|&b| b == separator) {
+                    Some(i) => {
+                        end += i + 1;
+                        left -= 1;
+                    },
+                    None => {
+                        end = buffer.len();
+                        break;
+                    }
+                }
+            }
+            output.write_all(&buffer[..end])?;
+            end
+        };
+        input.consume(length);
+    }
+    Ok(())
+}
+
+/// Reads past the first *n* lines of *input*.
+pub fn skip_lines<R: BufRead>(input: R, n: u64, separator: u8) -> io::Result<()> {
+    copy_lines(input, &mut io::sink(), n, separator)
+}
+
+/// Reads past the first *n* bytes of *input*.
+pub fn skip_bytes<R: BufRead>(input: R, n: u64) -> io::Result<()> {
+    io::copy(&mut input.take(n), &mut io::sink())?;
+    Ok(())
+}
+
+/// Reads all of *input*, holding back the last *k* bytes in a ring buffer, which is returned
+/// at the end. Bytes that fall out of the front of the ring are handed to *spill* as they go,
+/// so this works on pipes without knowing how long they are.
+pub fn hold_last_bytes<R, F>(mut input: R, k: u64, mut spill: F) -> io::Result<VecDeque<u8>>
+    where R: BufRead, F: FnMut(&[u8]) -> io::Result<()>
+{
+    // more than could ever be held in memory is the same as holding everything back
+    let k = usize::try_from(k).unwrap_or(usize::MAX);
+    let mut held: VecDeque<u8> = VecDeque::new();
+    loop {
+        let length = {
+            let buffer = input.fill_buf()?;
+            if buffer.is_empty() {
+                break;
+            }
+            held.extend(buffer);
+            if held.len() > k {
+                let extra = held.len() - k;
+                let (front, back) = held.as_slices();
+                if extra <= front.len() {
+                    spill(&front[..extra])?;
+                } else {
+                    spill(front)?;
+                    spill(&back[..extra - front.len()])?;
+                }
+                held.drain(..extra);
+            }
+            buffer.len()
+        };
+        input.consume(length);
+    }
+    Ok(held)
+}
+
+/// Reads all of *input*, holding back the last *k* lines, which are returned at the end.
+/// Lines end with *separator*, and the ones that fall out of the front are handed to *spill*.
+pub fn hold_last_lines<R, F>(mut input: R, k: u64, separator: u8, mut spill: F) -> io::Result<VecDeque<Vec<u8>>>
+    where R: BufRead, F: FnMut(&[u8]) -> io::Result<()>
+{
+    let k = usize::try_from(k).unwrap_or(usize::MAX);
+    let mut held: VecDeque<Vec<u8>> = VecDeque::new();
+    // the buffer of the last line to go out is reused for the next one coming in
+    let mut line = Vec::new();
+    while input.read_until(separator, &mut line)? > 0 {
+        if k == 0 {
+            spill(&line)?;
+            line.clear();
+            continue;
+        }
+        held.push_back(line);
+        line = if held.len() > k {
+            match held.pop_front() {
+                Some(mut l) => {
+                    spill(&l)?;
+                    l.clear();
+                    l
+                },
+                None => Vec::new()
+            }
+        } else {
+            Vec::new()
+        };
+    }
+    Ok(held)
+}
+
+#[cfg(test)]
+mod tests;
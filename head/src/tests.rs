@@ -0,0 +1,222 @@
+/*
+ * head/tests.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+extern crate assert_cli;
+
+use crate::*;
+use assert_cli::Assert;
+
+fn run(text: &[u8], mode: Mode, separator: u8) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::new();
+    head(text, &mut output, &mode, separator).unwrap();
+    output
+}
+
+#[test]
+fn t_parse_size() {
+    assert_eq!(parse_size("10"), Ok(10));
+    assert_eq!(parse_size("+3"), Ok(3));
+    assert_eq!(parse_size("2b"), Ok(1024));
+    assert_eq!(parse_size("1K"), Ok(1024));
+    assert_eq!(parse_size("1kB"), Ok(1000));
+    assert_eq!(parse_size("1KiB"), Ok(1024));
+    assert_eq!(parse_size("3MB"), Ok(3_000_000));
+    assert_eq!(parse_size("1E"), Ok(1 << 60));
+    assert_eq!(parse_size("1Z"), Err(SizeError::Overflow));
+    assert_eq!(parse_size("99999999999999999999"), Err(SizeError::Overflow));
+    assert_eq!(parse_size("x"), Err(SizeError::Invalid));
+    assert_eq!(parse_size("5Q"), Err(SizeError::Invalid));
+    assert_eq!(parse_size(""), Err(SizeError::Invalid));
+}
+
+#[test]
+fn t_lines() {
+    assert_eq!(run(b"a\nb\nc\n", Mode::Lines(2), b'\n'), b"a\nb\n");
+    assert_eq!(run(b"a\nb\nc", Mode::Lines(5), b'\n'), b"a\nb\nc");
+    assert_eq!(run(b"a\nb\nc\n", Mode::Lines(0), b'\n'), b"");
+    assert_eq!(run(b"a\nb\nc", Mode::AllButLines(1), b'\n'), b"a\nb\n");
+    assert_eq!(run(b"a\nb\nc\n", Mode::AllButLines(0), b'\n'), b"a\nb\nc\n");
+    assert_eq!(run(b"a\nb\n", Mode::AllButLines(5), b'\n'), b"");
+    assert_eq!(run(b"a\0b\nc\0d", Mode::Lines(1), b'\0'), b"a\0");
+    assert_eq!(run(b"a\0b\nc\0d", Mode::AllButLines(1), b'\0'), b"a\0b\nc\0");
+}
+
+#[test]
+fn t_bytes() {
+    let binary: Vec<u8> = (0..=255).collect();
+    assert_eq!(run(&binary, Mode::Bytes(300), b'\n'), binary);
+    assert_eq!(run(&binary, Mode::Bytes(200), b'\n'), &binary[..200]);
+    assert_eq!(run(&binary, Mode::AllButBytes(56), b'\n'), &binary[..200]);
+    assert_eq!(run(&binary, Mode::AllButBytes(0), b'\n'), binary);
+    assert_eq!(run(&binary, Mode::AllButBytes(1000), b'\n'), b"");
+}
+
+#[test]
+fn t_hold_last() {
+    let mut spilled: Vec<u8> = Vec::new();
+    let held = hold_last_lines(&b"1\n2\n3\n4"[..], 2, b'\n', |line| {
+        spilled.extend(line);
+        Ok(())
+    }).unwrap();
+    assert_eq!(spilled, b"1\n2\n");
+    assert_eq!(held, vec![b"3\n".to_vec(), b"4".to_vec()]);
+
+    let held = hold_last_bytes(&b"abcdef"[..], 4, |_| Ok(())).unwrap();
+    assert_eq!(held.into_iter().collect::<Vec<u8>>(), b"cdef");
+}
+
+#[test]
+fn t_options_cli() {
+    Assert::main_binary()
+        .stdin("1\n2\n3\n4\n5\n")
+        .with_args(&["-2"])
+        .stdout().is("1\n2")
+        .unwrap();
+    Assert::main_binary()
+        .stdin("1\n2\n3\n4\n5\n")
+        .with_args(&["--lines=-3", "-"])
+        .stdout().is("1\n2")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["Cargo.toml", "-n1", "-v"])
+        .stdout().is("==> Cargo.toml <==\n[package]")
+        .unwrap();
+}
+
+#[test]
+fn t_headers_cli() {
+    Assert::main_binary()
+        .with_args(&["-n", "1", "Cargo.toml", "missing", "Cargo.toml"])
+        .fails_with(1)
+        .stdout().is("==> Cargo.toml <==\n[package]\n\n==> Cargo.toml <==\n[package]")
+        .stderr().is("head: cannot open 'missing' for reading: No such file or directory")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-q", "-n", "1", "Cargo.toml", "-"])
+        .stdin("one\ntwo\n")
+        .stdout().is("[package]\none")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-v", "-n", "1", "-"])
+        .stdin("one\ntwo\n")
+        .stdout().is("==> standard input <==\none")
+        .unwrap();
+}
+
+#[test]
+fn t_all_but_cli() {
+    Assert::main_binary()
+        .stdin("1\n2\n3\n4\n5\n")
+        .with_args(&["-n", "-2"])
+        .stdout().is("1\n2\n3")
+        .unwrap();
+    Assert::main_binary()
+        .stdin("abcdef")
+        .with_args(&["-c", "-2"])
+        .stdout().is("abcd")
+        .unwrap();
+    Assert::main_binary()
+        .stdin("1\n2\n")
+        .with_args(&["-n", "-5"])
+        .stdout().is("")
+        .unwrap();
+}
+
+#[test]
+fn t_suffixes_cli() {
+    let text = "a".repeat(2000);
+    Assert::main_binary()
+        .stdin(text.as_str())
+        .with_args(&["-c", "1KiB"])
+        .stdout().is(&text[..1024])
+        .unwrap();
+    Assert::main_binary()
+        .stdin(text.as_str())
+        .with_args(&["-c", "1kB"])
+        .stdout().is(&text[..1000])
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-c", "1Z", "Cargo.toml"])
+        .fails_with(1)
+        .stderr().contains("invalid number of bytes: '1Z': Value too large for defined data type")
+        .unwrap();
+}
+
+#[test]
+fn t_count_forms_cli() {
+    for args in &[&["-5"][..], &["-n5"], &["-n", "5"], &["--lines=5"], &["--lines", "5"]] {
+        Assert::main_binary()
+            .stdin("1\n2\n3\n4\n5\n6\n7\n")
+            .with_args(args)
+            .stdout().is("1\n2\n3\n4\n5")
+            .unwrap();
+    }
+    Assert::main_binary()
+        .stdin("abcdef")
+        .with_args(&["-3c"])
+        .stdout().is("abc")
+        .unwrap();
+    // the last of -n and -c is the one used
+    Assert::main_binary()
+        .stdin("abcdef\nghi\n")
+        .with_args(&["-c", "2", "-n", "1"])
+        .stdout().is("abcdef")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-n", "1x"])
+        .fails_with(1)
+        .stderr().contains("invalid number of lines: '1x'")
+        .unwrap();
+}
+
+#[test]
+fn t_binary_cli() {
+    let binary: Vec<u8> = (0..=255).rev().collect();
+    Assert::main_binary()
+        .stdin(binary.clone())
+        .with_args(&["-c", "200"])
+        .stdout().is(&binary[..200])
+        .unwrap();
+    // the only newline is byte 246, and the last line after it has none
+    Assert::main_binary()
+        .stdin(binary.clone())
+        .with_args(&["-n", "-1"])
+        .stdout().is(&binary[..246])
+        .unwrap();
+}
+
+#[test]
+fn t_zero_terminated_cli() {
+    Assert::main_binary()
+        .stdin(&b"a\nb\0c\0d"[..])
+        .with_args(&["-z", "-n", "2"])
+        .stdout().is(&b"a\nb\0c\0"[..])
+        .unwrap();
+    Assert::main_binary()
+        .stdin(&b"a\nb\0c\0d"[..])
+        .with_args(&["--zero-terminated", "-n", "-1"])
+        .stdout().is(&b"a\nb\0c\0"[..])
+        .unwrap();
+}
+
+#[test]
+fn t_obsolete() {
+    let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    let head = cli::Obsolete { plus: false, multipliers: "bkm", flags: "qv" };
+    let tail = cli::Obsolete { plus: true, multipliers: "b", flags: "f" };
+    assert_eq!(cli::obsolete(args(&["head", "-5", "f"]), &head), args(&["head", "-n", "5", "f"]));
+    // the multipliers mean bytes, and 'c' goes back to no multiplier
+    assert_eq!(cli::obsolete(args(&["head", "-2k"]), &head), args(&["head", "-c", "2K"]));
+    assert_eq!(cli::obsolete(args(&["head", "-2kc"]), &head), args(&["head", "-c", "2"]));
+    assert_eq!(cli::obsolete(args(&["head", "-2kl"]), &head), args(&["head", "-n", "2K"]));
+    assert_eq!(cli::obsolete(args(&["head", "-2v"]), &head), args(&["head", "-n", "2", "-v"]));
+    assert_eq!(cli::obsolete(args(&["head", "+2"]), &head), args(&["head", "+2"]));
+    assert_eq!(cli::obsolete(args(&["head", "-2f"]), &head), args(&["head", "-2f"]));
+    assert_eq!(cli::obsolete(args(&["tail", "+20f"]), &tail), args(&["tail", "-n", "+20", "-f"]));
+    assert_eq!(cli::obsolete(args(&["tail", "-1b"]), &tail), args(&["tail", "-c", "1b"]));
+    assert_eq!(cli::obsolete(args(&["tail", "-1k"]), &tail), args(&["tail", "-1k"]));
+}
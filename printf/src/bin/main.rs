@@ -0,0 +1,62 @@
+/*
+ * printf/main.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+extern crate printf;
+
+use printf::printf;
+use std::{env, io, process};
+use std::io::{BufWriter, Write, stderr, stdout};
+use std::os::unix::ffi::OsStrExt;
+
+fn usage() {
+    println!("USAGE: printf FORMAT [ARGUMENT]...");
+    println!("Print ARGUMENT(s) according to FORMAT, which is used over again while there are");
+    println!("arguments left for it.");
+    println!();
+    println!("FORMAT is printed as it is, except for backslash escapes and these conversions:");
+    println!("    %%  a single %");
+    println!("    %b  ARGUMENT as a string with backslash escapes interpreted, like echo -e");
+    println!("    %q  ARGUMENT quoted so that the shell can read it back");
+    println!("    %c  the first character of ARGUMENT");
+    println!("    %s  ARGUMENT as a string");
+    println!("    %d, %i  a signed decimal integer");
+    println!("    %o, %u, %x, %X  an unsigned octal, decimal or hexadecimal integer");
+    println!("    %e, %f, %g, %E, %F, %G  a floating point number");
+    println!("Conversions other than %b and %q take the flags - + space # 0, a width and a");
+    println!("precision, like %-8.3f. A width or precision of * is taken from the next ARGUMENT.");
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<_> = env::args_os().skip(1).collect();
+    if args.len() == 1 && args[0] == "--help" {
+        usage();
+        return Ok(());
+    }
+    if args.len() == 1 && args[0] == "--version" {
+        println!("printf {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    let args = if args.first().is_some_and(|a| a == "--") { &args[1..] } else { &args[..] };
+    if args.is_empty() {
+        writeln!(stderr(), "printf: missing operand\nTry 'printf --help' for more information.")?;
+        process::exit(1);
+    }
+
+    let arguments: Vec<&[u8]> = args[1..].iter().map(|a| a.as_bytes()).collect();
+    let stdout = stdout();
+    let mut output = BufWriter::new(stdout.lock());
+    let result = printf(args[0].as_bytes(), &arguments, &mut output);
+    output.flush()?;
+    match result {
+        Ok(true) => Ok(()),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            writeln!(stderr(), "{}", e)?;
+            process::exit(1);
+        }
+    }
+}
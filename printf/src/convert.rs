@@ -0,0 +1,327 @@
+/*
+ * printf/convert.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+use std::io::{stderr, Write};
+use std::str;
+
+/// How a numeric argument fell short.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Problem {
+    /// Nothing at the start of it was a number
+    NotNumeric,
+    /// It started with a number, but had something else after
+    NotConverted,
+    /// It was too big or small for the conversion
+    OutOfRange
+}
+
+/// Tells the user what was wrong with the numeric argument *text*, the way GNU printf does.
+pub fn warn(text: &[u8], problem: Problem) {
+    let message = match problem {
+        Problem::NotNumeric => "expected a numeric value",
+        Problem::NotConverted => "value not completely converted",
+        Problem::OutOfRange => "Numerical result out of range"
+    };
+    let text = String::from_utf8_lossy(text).replace('\\', "\\\\");
+    let _ = writeln!(stderr(), "printf: '{}': {}", text, message);
+}
+
+/// The value of a character constant like `'A` or `"A`: the character after the quote.
+fn character(text: &[u8]) -> Option<u32> {
+    match text.first() {
+        Some(b'\'') | Some(b'"') if text.len() > 1 => {
+            let rest = &text[1..];
+            let length = rest.len().min(4);
+            // the first character in UTF-8, or else the first byte
+            let value = (1..=length).rev()
+                .filter_map(|n| str::from_utf8(&rest[..n]).ok())
+                .filter_map(|s| s.chars().next())
+                .next()
+                .map(|c| c as u32)
+                .unwrap_or(rest[0] as u32);
+            Some(value)
+        },
+        _ => None
+    }
+}
+
+fn trim_start(text: &[u8]) -> &[u8] {
+    let start = text.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(text.len());
+    &text[start..]
+}
+
+/// Reads an integer argument like C's `strtoimax`: optional blanks and sign, then decimal,
+/// 0x hexadecimal or 0 octal digits, or a character constant. Anything that isn't right is
+/// reported, and the best value that can be made of it is used. An *unsigned* conversion
+/// takes values up to `u64::MAX`, and negative ones wrap around.
+pub fn integer(text: &[u8], unsigned: bool) -> (i128, Option<Problem>) {
+    let trimmed = trim_start(text);
+    if let Some(c) = character(trimmed) {
+        return (c as i128, None);
+    }
+    if trimmed.is_empty() {
+        let problem = if text.is_empty() { None } else { Some(Problem::NotNumeric) };
+        return (0, problem);
+    }
+
+    let (negative, rest) = match trimmed[0] {
+        b'-' => (true, &trimmed[1..]),
+        b'+' => (false, &trimmed[1..]),
+        _ => (false, trimmed)
+    };
+    let hex = rest.len() > 2 && (rest.starts_with(b"0x") || rest.starts_with(b"0X")) && rest[2].is_ascii_hexdigit();
+    let (base, digits) = if hex {
+        (16, &rest[2..])
+    } else if rest.starts_with(b"0") {
+        (8, rest)
+    } else {
+        (10, rest)
+    };
+
+    let mut magnitude: u128 = 0;
+    let mut count = 0;
+    let mut overflow = false;
+    for &b in digits {
+        match (b as char).to_digit(base) {
+            Some(d) => {
+                magnitude = magnitude.saturating_mul(base as u128).saturating_add(d as u128);
+                if magnitude > u64::MAX as u128 {
+                    overflow = true;
+                }
+            },
+            None => break
+        }
+        count += 1;
+    }
+    if count == 0 {
+        return (0, Some(Problem::NotNumeric));
+    }
+
+    let (value, problem) = if unsigned {
+        if overflow {
+            (u64::MAX as i128, Some(Problem::OutOfRange))
+        } else if negative {
+            ((magnitude as u64).wrapping_neg() as i128, None)
+        } else {
+            (magnitude as i128, None)
+        }
+    } else {
+        let value = if negative { -(magnitude as i128) } else { magnitude as i128 };
+        if value > i64::MAX as i128 {
+            (i64::MAX as i128, Some(Problem::OutOfRange))
+        } else if value < i64::MIN as i128 {
+            (i64::MIN as i128, Some(Problem::OutOfRange))
+        } else {
+            (value, None)
+        }
+    };
+    if problem.is_none() && count < digits.len() {
+        return (value, Some(Problem::NotConverted));
+    }
+    (value, problem)
+}
+
+/// Reads a floating point argument like C's `strtod`, taking the longest start of it that is
+/// a number. Character constants and 0x hexadecimal integers are understood too.
+pub fn float(text: &[u8]) -> (f64, Option<Problem>) {
+    let trimmed = trim_start(text);
+    if let Some(c) = character(trimmed) {
+        return (c as f64, None);
+    }
+    if trimmed.is_empty() {
+        let problem = if text.is_empty() { None } else { Some(Problem::NotNumeric) };
+        return (0.0, problem);
+    }
+
+    let unsigned = trimmed.strip_prefix(b"-").or_else(|| trimmed.strip_prefix(b"+")).unwrap_or(trimmed);
+    if unsigned.len() > 2 && (unsigned.starts_with(b"0x") || unsigned.starts_with(b"0X")) {
+        let (value, problem) = integer(trimmed, false);
+        return (value as f64, problem);
+    }
+
+    let text = String::from_utf8_lossy(trimmed);
+    for end in (1..=text.len()).rev().filter(|&e| text.is_char_boundary(e)) {
+        if let Ok(value) = text[..end].parse::<f64>() {
+            let problem = if end < text.len() { Some(Problem::NotConverted) } else { None };
+            return (value, problem);
+        }
+    }
+    (0.0, Some(Problem::NotNumeric))
+}
+
+/// Writes an integer for %d, %i, %o, %u, %x or %X, with at least *precision* digits. *sign* is
+/// what goes in front of a value that isn't negative: nothing, '+' or ' '. *alternate* is the
+/// # flag, which puts 0 in front of octal and 0x in front of hexadecimal. Padding is left to
+/// the caller, so this gives the prefix, the number of zeros the precision puts in front of the
+/// digits, and the digits separately.
+pub fn format_integer(value: i128, conversion: u8, precision: Option<usize>, sign: &str, alternate: bool) -> (String, usize, String) {
+    let magnitude = value.unsigned_abs();
+    let mut digits = match conversion {
+        b'o' => format!("{:o}", magnitude),
+        b'x' => format!("{:x}", magnitude),
+        b'X' => format!("{:X}", magnitude),
+        _ => format!("{}", magnitude)
+    };
+    let mut leading = 0;
+    if let Some(p) = precision {
+        if p == 0 && magnitude == 0 {
+            digits.clear();
+        }
+        leading = p.saturating_sub(digits.len());
+    }
+
+    let mut prefix = String::new();
+    if value < 0 {
+        prefix.push('-');
+    } else if conversion == b'd' || conversion == b'i' {
+        prefix.push_str(sign);
+    }
+    if alternate {
+        match conversion {
+            b'o' if leading == 0 && !digits.starts_with('0') => digits.insert(0, '0'),
+            b'x' if magnitude != 0 => prefix.push_str("0x"),
+            b'X' if magnitude != 0 => prefix.push_str("0X"),
+            _ => {}
+        }
+    }
+    (prefix, leading, digits)
+}
+
+/// `value` in C's %e style, with the exponent signed and at least two digits.
+fn exponent(value: f64, precision: usize, alternate: bool) -> String {
+    let text = format!("{:.*e}", precision, value);
+    let (mantissa, power) = text.split_at(text.find('e').unwrap_or(text.len()));
+    let power: i32 = power.get(1..).and_then(|p| p.parse().ok()).unwrap_or(0);
+    let point = if alternate && precision == 0 { "." } else { "" };
+    format!("{}{}e{}{:02}", mantissa, point, if power < 0 { '-' } else { '+' }, power.abs())
+}
+
+/// Takes the zeros off the end of the fraction in *number*, and the point if nothing is left
+/// after it, leaving any exponent where it is.
+fn strip_zeros(number: &str) -> String {
+    let (mantissa, power) = number.split_at(number.find('e').unwrap_or(number.len()));
+    if !mantissa.contains('.') {
+        return number.to_owned();
+    }
+    let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+    format!("{}{}", mantissa, power)
+}
+
+/// Writes a float for %e, %f or %g, or their capital forms, as C would. Like
+/// `format_integer()`, this gives the sign and the rest separately so the caller can pad.
+pub fn format_float(value: f64, conversion: u8, precision: Option<usize>, sign: &str, alternate: bool) -> (String, String) {
+    let prefix = if value.is_sign_negative() { "-" } else { sign }.to_owned();
+    let magnitude = value.abs();
+    let precision = precision.unwrap_or(6);
+
+    let body = if magnitude.is_nan() {
+        "nan".to_owned()
+    } else if magnitude.is_infinite() {
+        "inf".to_owned()
+    } else {
+        match conversion.to_ascii_lowercase() {
+            b'e' => exponent(magnitude, precision, alternate),
+            b'g' => {
+                // %g is %e when the exponent is under -4 or at least the precision, else %f
+                let significant = precision.max(1);
+                let power: i32 = if magnitude == 0.0 {
+                    0
+                } else {
+                    let text = format!("{:.*e}", significant - 1, magnitude);
+                    text[text.find('e').unwrap_or(0) + 1..].parse().unwrap_or(0)
+                };
+                let text = if power < -4 || power >= significant as i32 {
+                    exponent(magnitude, significant - 1, alternate)
+                } else {
+                    let decimals = (significant as i32 - 1 - power) as usize;
+                    let point = if alternate && decimals == 0 { "." } else { "" };
+                    format!("{:.*}{}", decimals, magnitude, point)
+                };
+                if alternate { text } else { strip_zeros(&text) }
+            },
+            _ => {
+                let point = if alternate && precision == 0 { "." } else { "" };
+                format!("{:.*}{}", precision, magnitude, point)
+            }
+        }
+    };
+    if conversion.is_ascii_uppercase() {
+        (prefix, body.to_uppercase())
+    } else {
+        (prefix, body)
+    }
+}
+
+/// Whether *byte* can appear in a word for the shell without being quoted. `~` and `#` are
+/// only special at the start.
+fn is_safe(byte: u8, first: bool) -> bool {
+    byte.is_ascii_alphanumeric() || b"%+,-./:@_{}".contains(&byte) || (!first && (byte == b'~' || byte == b'#'))
+}
+
+fn is_printable(byte: u8) -> bool {
+    (0x20..0x7f).contains(&byte)
+}
+
+/// Quotes *text* for %q so that a shell would read it back as the same word, the way GNU
+/// printf does in the C locale: as it is if nothing in it is special, in double quotes if it
+/// only has single quotes in it, or else in single quotes, with `$'...'` for bytes that can't
+/// be printed.
+pub fn quote(text: &[u8]) -> Vec<u8> {
+    if text.is_empty() {
+        return b"''".to_vec();
+    }
+    if text.iter().enumerate().all(|(i, &b)| is_safe(b, i == 0)) {
+        return text.to_vec();
+    }
+    let printable = text.iter().all(|&b| is_printable(b));
+    if printable && text.contains(&b'\'') && !text.iter().any(|b| b"$`\\\"!".contains(b)) {
+        let mut quoted = vec![b'"'];
+        quoted.extend_from_slice(text);
+        quoted.push(b'"');
+        return quoted;
+    }
+
+    let mut quoted = vec![b'\''];
+    let mut after_dollar = false;
+    let mut i = 0;
+    while i < text.len() {
+        let b = text[i];
+        if is_printable(b) {
+            if b == b'\'' {
+                quoted.extend_from_slice(b"'\\''");
+            } else {
+                quoted.push(b);
+            }
+            after_dollar = false;
+            i += 1;
+            continue;
+        }
+        quoted.extend_from_slice(b"'$'");
+        while i < text.len() && !is_printable(text[i]) {
+            match text[i] {
+                0x07 => quoted.extend_from_slice(b"\\a"),
+                0x08 => quoted.extend_from_slice(b"\\b"),
+                b'\t' => quoted.extend_from_slice(b"\\t"),
+                b'\n' => quoted.extend_from_slice(b"\\n"),
+                0x0b => quoted.extend_from_slice(b"\\v"),
+                0x0c => quoted.extend_from_slice(b"\\f"),
+                b'\r' => quoted.extend_from_slice(b"\\r"),
+                other => quoted.extend_from_slice(format!("\\{:03o}", other).as_bytes())
+            }
+            i += 1;
+        }
+        quoted.extend_from_slice(b"''");
+        after_dollar = true;
+    }
+    // a $'...' at the end doesn't need an empty '' after it
+    if after_dollar {
+        quoted.pop();
+    } else {
+        quoted.push(b'\'');
+    }
+    quoted
+}
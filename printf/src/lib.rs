@@ -0,0 +1,343 @@
+/*
+ * printf/lib.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+//!
+//! printf - format and print data
+//!
+//! ```text
+//! $ printf '%-5s|%03d|%.2f\n' ab 7 3.14159
+//! ab   |007|3.14
+//! ```
+//!
+#[macro_use]
+extern crate quick_error;
+extern crate echo;
+
+use echo::{decode, escape, Escape, Style};
+use std::io::{self, stderr, Write};
+
+pub mod convert;
+
+use crate::convert::{format_float, format_integer, quote, warn, Problem};
+
+quick_error! {
+    /// Problems printf can run into
+    #[derive(Debug)]
+    pub enum Errors {
+        Output(err: io::Error) {
+            display("printf: {0}", err) from()
+            cause(err)
+        }
+
+        Conversion(spec: String) {
+            display("printf: {0}: invalid conversion specification", spec)
+        }
+
+        Width(text: String) {
+            display("printf: invalid field width: '{0}'", text)
+        }
+
+        Precision(text: String) {
+            display("printf: invalid precision: '{0}'", text)
+        }
+    }
+}
+
+/// Whether to keep going after a piece of the format.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Flow {
+    Continue,
+    /// \c was found, so nothing more is printed
+    Stop
+}
+
+/// The arguments, handed out to conversions in order. Conversions that run out get empty
+/// strings and zeros.
+struct Arguments<'a> {
+    list: &'a [&'a [u8]],
+    next: usize,
+    /// Whether a numeric argument was bad
+    trouble: bool
+}
+
+impl<'a> Arguments<'a> {
+    fn string(&mut self) -> &'a [u8] {
+        let arg = self.list.get(self.next).copied().unwrap_or(b"");
+        self.next += 1;
+        arg
+    }
+
+    /// The next argument as an integer. A bad one is reported, after flushing *output* so the
+    /// message comes after what has been printed so far.
+    fn integer<W: Write>(&mut self, output: &mut W, unsigned: bool) -> io::Result<i128> {
+        let arg = self.string();
+        let (value, problem) = convert::integer(arg, unsigned);
+        self.check(output, arg, problem)?;
+        Ok(value)
+    }
+
+    /// The next argument as a floating point number, reported like `integer()` if it is bad.
+    fn float<W: Write>(&mut self, output: &mut W) -> io::Result<f64> {
+        let arg = self.string();
+        let (value, problem) = convert::float(arg);
+        self.check(output, arg, problem)?;
+        Ok(value)
+    }
+
+    /// The argument handed out last, to say which one was bad.
+    fn last(&self) -> String {
+        let arg = self.next.checked_sub(1).and_then(|i| self.list.get(i)).copied().unwrap_or(b"");
+        String::from_utf8_lossy(arg).into_owned()
+    }
+
+    fn check<W: Write>(&mut self, output: &mut W, arg: &[u8], problem: Option<Problem>) -> io::Result<()> {
+        if let Some(p) = problem {
+            output.flush()?;
+            warn(arg, p);
+            self.trouble = true;
+        }
+        Ok(())
+    }
+}
+
+/// One % conversion from the format, like `%-8.3f`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Spec {
+    /// Pad on the right instead of the left: -
+    pub left: bool,
+    /// What to put in front of a number that isn't negative: '+', ' ' or nothing
+    pub sign: &'static str,
+    /// The # flag
+    pub alternate: bool,
+    /// Pad numbers with zeros instead of spaces: 0
+    pub zero: bool,
+    pub width: usize,
+    pub precision: Option<usize>,
+    pub conversion: u8
+}
+
+/// The largest width or precision, as C's printf takes them as an int.
+const LIMIT: usize = i32::MAX as usize;
+
+/// Writes *count* copies of *byte*, a piece at a time, since a width can ask for gigabytes.
+fn fill<W: Write>(output: &mut W, byte: u8, mut count: usize) -> io::Result<()> {
+    let piece = [byte; 4096];
+    while count > 0 {
+        let n = count.min(piece.len());
+        output.write_all(&piece[..n])?;
+        count -= n;
+    }
+    Ok(())
+}
+
+/// Writes *body* padded with spaces to `spec.width`. Numbers padded with zeros put them
+/// between the *prefix* and the digits, after the *leading* zeros a precision asks for.
+fn pad<W: Write>(output: &mut W, spec: &Spec, prefix: &[u8], leading: usize, body: &[u8], zeros: bool) -> io::Result<()> {
+    let fill_count = spec.width.saturating_sub(prefix.len() + leading + body.len());
+    if !spec.left && !zeros {
+        fill(output, b' ', fill_count)?;
+    }
+    output.write_all(prefix)?;
+    if zeros {
+        fill(output, b'0', fill_count)?;
+    }
+    fill(output, b'0', leading)?;
+    output.write_all(body)?;
+    if spec.left {
+        fill(output, b' ', fill_count)?;
+    }
+    Ok(())
+}
+
+/// Reads the digits at *i* in *format* as a number, moving *i* past them. Gives back the
+/// digits instead if they are more than `LIMIT`.
+fn number(format: &[u8], i: &mut usize) -> Result<usize, String> {
+    let start = *i;
+    let mut n: usize = 0;
+    while let Some(d) = format.get(*i).filter(|b| b.is_ascii_digit()) {
+        n = n.saturating_mul(10).saturating_add((d - b'0') as usize);
+        *i += 1;
+    }
+    if n > LIMIT {
+        return Err(String::from_utf8_lossy(&format[start..*i]).into_owned());
+    }
+    Ok(n)
+}
+
+/// Reads the conversion that starts with the '%' at `format[start]`, taking * widths and
+/// precisions from *args*. Gives the conversion and where the format carries on after it.
+/// Flags that don't go with the conversion make it invalid, as in GNU printf.
+fn parse_spec<W: Write>(format: &[u8], start: usize, output: &mut W, args: &mut Arguments) -> Result<(Spec, usize), Errors> {
+    let invalid = |end: usize| Errors::Conversion(String::from_utf8_lossy(&format[start..end.min(format.len())]).into_owned());
+    let mut spec = Spec::default();
+    let mut i = start + 1;
+    // %%, %b and %q take no flags, width or precision at all
+    if let Some(&c) = format.get(i).filter(|&&c| c == b'%' || c == b'b' || c == b'q') {
+        spec.conversion = c;
+        return Ok((spec, i + 1));
+    }
+
+    let mut ok: Vec<u8> = b"cdeEfFgGiosuxX".to_vec();
+    loop {
+        match format.get(i) {
+            Some(b'\'') => ok.retain(|c| !b"ceEosxX".contains(c)),
+            Some(b'-') => spec.left = true,
+            Some(b'+') => spec.sign = "+",
+            Some(b' ') => {
+                if spec.sign.is_empty() {
+                    spec.sign = " ";
+                }
+            },
+            Some(b'#') => {
+                spec.alternate = true;
+                ok.retain(|c| !b"cdisu".contains(c));
+            },
+            Some(b'0') => {
+                spec.zero = true;
+                ok.retain(|c| !b"cs".contains(c));
+            },
+            _ => break
+        }
+        i += 1;
+    }
+
+    if format.get(i) == Some(&b'*') {
+        i += 1;
+        let width = args.integer(output, false)?;
+        if width.unsigned_abs() > LIMIT as u128 {
+            return Err(Errors::Width(args.last()));
+        }
+        spec.left |= width < 0;
+        spec.width = width.unsigned_abs() as usize;
+    } else {
+        spec.width = number(format, &mut i).map_err(Errors::Width)?;
+    }
+    if format.get(i) == Some(&b'.') {
+        i += 1;
+        ok.retain(|&c| c != b'c');
+        if format.get(i) == Some(&b'*') {
+            i += 1;
+            let precision = args.integer(output, false)?;
+            if precision.unsigned_abs() > LIMIT as u128 {
+                return Err(Errors::Precision(args.last()));
+            }
+            // a negative precision is the same as none at all
+            spec.precision = if precision < 0 { None } else { Some(precision as usize) };
+        } else {
+            spec.precision = Some(number(format, &mut i).map_err(Errors::Precision)?);
+        }
+    }
+    // C's length modifiers mean nothing here
+    while matches!(format.get(i), Some(c) if b"hlLjtz".contains(c)) {
+        i += 1;
+    }
+
+    match format.get(i) {
+        Some(&c) if ok.contains(&c) => {
+            spec.conversion = c;
+            Ok((spec, i + 1))
+        },
+        _ => Err(invalid(i + 1))
+    }
+}
+
+/// Prints one conversion, taking what it needs from *args*.
+fn convert<W: Write>(output: &mut W, spec: &Spec, args: &mut Arguments) -> Result<Flow, Errors> {
+    // zeros are for numbers that aren't already left justified or given a precision
+    let zeros = spec.zero && !spec.left;
+    match spec.conversion {
+        b'%' => output.write_all(b"%")?,
+        b's' => {
+            let arg = args.string();
+            let end = spec.precision.unwrap_or(arg.len()).min(arg.len());
+            pad(output, spec, b"", 0, &arg[..end], false)?;
+        },
+        b'b' => {
+            let (decoded, stop) = decode(args.string(), Style::Argument)?;
+            output.write_all(&decoded)?;
+            if stop {
+                return Ok(Flow::Stop);
+            }
+        },
+        b'q' => output.write_all(&quote(args.string()))?,
+        b'c' => {
+            let arg = args.string();
+            pad(output, spec, b"", 0, &[arg.first().copied().unwrap_or(0)], false)?;
+        },
+        b'd' | b'i' | b'o' | b'u' | b'x' | b'X' => {
+            let unsigned = spec.conversion != b'd' && spec.conversion != b'i';
+            let value = args.integer(output, unsigned)?;
+            let (prefix, leading, digits) = format_integer(value, spec.conversion, spec.precision, spec.sign, spec.alternate);
+            pad(output, spec, prefix.as_bytes(), leading, digits.as_bytes(), zeros && spec.precision.is_none())?;
+        },
+        _ => {
+            let value = args.float(output)?;
+            let (prefix, body) = format_float(value, spec.conversion, spec.precision, spec.sign, spec.alternate);
+            pad(output, spec, prefix.as_bytes(), 0, body.as_bytes(), zeros && value.is_finite())?;
+        }
+    }
+    Ok(Flow::Continue)
+}
+
+/// Goes through *format* once, writing it to *output* with its escapes decoded and its
+/// conversions filled in from *args*.
+fn print_once<W: Write>(format: &[u8], output: &mut W, args: &mut Arguments) -> Result<Flow, Errors> {
+    let mut i = 0;
+    while i < format.len() {
+        let plain = format[i..].iter().position(|&b| b == b'\\' || b == b'%').unwrap_or(format.len() - i);
+        output.write_all(&format[i..i + plain])?;
+        i += plain;
+        if i == format.len() {
+            break;
+        }
+
+        if format[i] == b'\\' {
+            let (decoded, length) = escape(&format[i..], Style::Format)?;
+            if decoded == Escape::Stop {
+                return Ok(Flow::Stop);
+            }
+            decoded.write(output, &format[i..i + length])?;
+            i += length;
+        } else {
+            let (spec, next) = parse_spec(format, i, output, args)?;
+            if convert(output, &spec, args)? == Flow::Stop {
+                return Ok(Flow::Stop);
+            }
+            i = next;
+        }
+    }
+    Ok(Flow::Continue)
+}
+
+/// Prints *arguments* to *output* as *format* says. The format is used over again for as
+/// long as there are arguments left for it to use. Returns whether every numeric argument was
+/// a good number; the ones that weren't have been reported on stderr.
+pub fn printf<W: Write>(format: &[u8], arguments: &[&[u8]], output: &mut W) -> Result<bool, Errors> {
+    let mut args = Arguments {
+        list: arguments,
+        next: 0,
+        trouble: false
+    };
+    loop {
+        let before = args.next;
+        if print_once(format, output, &mut args)? == Flow::Stop {
+            return Ok(!args.trouble);
+        }
+        if args.next == before || args.next >= arguments.len() {
+            break;
+        }
+    }
+    if args.next == 0 && !arguments.is_empty() {
+        output.flush()?;
+        writeln!(stderr(), "printf: warning: ignoring excess arguments, starting with '{}'",
+            String::from_utf8_lossy(arguments[0]))?;
+    }
+    Ok(!args.trouble)
+}
+
+#[cfg(test)]
+mod tests;
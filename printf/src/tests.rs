@@ -0,0 +1,146 @@
+/*
+ * printf/tests.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+extern crate assert_cli;
+
+use crate::convert::{self, quote, Problem};
+use crate::{printf, Errors};
+use assert_cli::Assert;
+
+fn run(format: &str, args: &[&str]) -> String {
+    let args: Vec<&[u8]> = args.iter().map(|a| a.as_bytes()).collect();
+    let mut output: Vec<u8> = Vec::new();
+    printf(format.as_bytes(), &args, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn t_strings() {
+    assert_eq!(run("%s-%s\n", &["a", "b", "c"]), "a-b\nc-\n");
+    assert_eq!(run("[%5s|%-5s|%.2s]", &["ab", "cd", "efg"]), "[   ab|cd   |ef]");
+    assert_eq!(run("%c%c", &["xyz", "w"]), "xw");
+    assert_eq!(run("100%%\n", &[]), "100%\n");
+    assert_eq!(run("%b|%s", &["a\\tb", "a\\tb"]), "a\tb|a\\tb");
+    assert_eq!(run("%b%s", &["x\\cy", "never"]), "x");
+}
+
+#[test]
+fn t_integers() {
+    assert_eq!(run("%d %i %d", &["42", "-7", "'A"]), "42 -7 65");
+    assert_eq!(run("%05d|%-5d|%+d|% d|%.3d", &["-42", "42", "3", "3", "7"]), "-0042|42   |+3| 3|007");
+    assert_eq!(run("%o %x %X %u", &["8", "255", "255", "-1"]), "10 ff FF 18446744073709551615");
+    assert_eq!(run("%#o %#x %#X", &["8", "255", "0"]), "010 0xff 0");
+    assert_eq!(run("%d %d", &["0x10", "010"]), "16 8");
+    assert_eq!(run("%*d|%-*d|", &["4", "1", "-3", "2"]), "   1|2  |");
+    assert_eq!(run("%ld %hd", &["1", "2"]), "1 2");
+    assert_eq!(run("%#.3o|%8.3d|%-6.2x|", &["8", "-5", "10"]), "010|    -005|0a    |");
+    assert_eq!(run("%05000d", &["1"]).len(), 5000);
+}
+
+#[test]
+fn t_floats() {
+    assert_eq!(run("%f %.2f %.0f", &["3.14159", "2.675", "2.5"]), "3.141590 2.67 2");
+    assert_eq!(run("%e %E %.1e", &["12345.678", "0.000123", "0"]), "1.234568e+04 1.230000E-04 0.0e+00");
+    assert_eq!(run("%g %g %g %g", &["100000", "1000000", "0.0001", "1e-5"]), "100000 1e+06 0.0001 1e-05");
+    assert_eq!(run("%#g %G %.3g", &["1", "1e20", "3.14159"]), "1.00000 1E+20 3.14");
+    assert_eq!(run("%010.3f|%-8.2e|", &["-3.14159", "12.5"]), "-00003.142|1.25e+01|");
+    assert_eq!(run("%f %5F %e", &["inf", "nan", "-inf"]), "inf   NAN -inf");
+    assert_eq!(run("%.*f", &["1", "2.25"]), "2.2");
+}
+
+#[test]
+fn t_arguments() {
+    assert_eq!(convert::integer(b"12", false), (12, None));
+    assert_eq!(convert::integer(b"", false), (0, None));
+    assert_eq!(convert::integer(b" +3", false), (3, None));
+    assert_eq!(convert::integer(b"12abc", false), (12, Some(Problem::NotConverted)));
+    assert_eq!(convert::integer(b"abc", false), (0, Some(Problem::NotNumeric)));
+    assert_eq!(convert::integer(b"9999999999999999999", false), (i64::MAX as i128, Some(Problem::OutOfRange)));
+    assert_eq!(convert::integer(b"18446744073709551615", true), (u64::MAX as i128, None));
+    assert_eq!(convert::integer(b"-1", true), (u64::MAX as i128, None));
+    assert_eq!(convert::float(b"1.5x"), (1.5, Some(Problem::NotConverted)));
+    assert_eq!(convert::float(b"0x10"), (16.0, None));
+}
+
+#[test]
+fn t_quote() {
+    assert_eq!(quote(b""), b"''");
+    assert_eq!(quote(b"plain-word_1.txt"), b"plain-word_1.txt");
+    assert_eq!(quote(b"a b"), b"'a b'");
+    assert_eq!(quote(b"it's"), b"\"it's\"");
+    assert_eq!(quote(b"it's $5"), b"'it'\\''s $5'");
+    assert_eq!(quote(b"a\tb"), b"'a'$'\\t''b'");
+    assert_eq!(quote(b"\n"), b"''$'\\n'");
+    assert_eq!(quote(b"~x"), b"'~x'");
+    assert_eq!(quote(b"x~"), b"x~");
+}
+
+#[test]
+fn t_escapes() {
+    assert_eq!(run("\\101\\0101\\x41\\t\\\"\\q", &[]), "A\u{8}1A\t\"\\q");
+    assert_eq!(run("%b", &["\\0101\\101"]), "AA");
+    assert_eq!(run("a\\cb%s", &["never"]), "a");
+    let mut output: Vec<u8> = Vec::new();
+    match printf(b"\\xg", &[], &mut output) {
+        Err(Errors::Output(e)) => assert_eq!(e.to_string(), "missing hexadecimal number in escape"),
+        _ => panic!("\\x without digits should be an error")
+    }
+}
+
+#[test]
+fn t_invalid_conversions() {
+    for format in &["%z", "%5%", "%5b", "%#s", "%0c", "%.1c", "%"] {
+        let mut output: Vec<u8> = Vec::new();
+        match printf(format.as_bytes(), &[b"1"], &mut output) {
+            Err(Errors::Conversion(_)) => {},
+            _ => panic!("{} should be an invalid conversion", format)
+        }
+    }
+}
+
+#[test]
+fn t_printf_cli() {
+    Assert::main_binary()
+        .with_args(&["%s=%d\n", "a", "1", "b", "2"])
+        .stdout().is("a=1\nb=2")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["%d\n", "12abc"])
+        .fails_with(1)
+        .stdout().is("12")
+        .stderr().is("printf: '12abc': value not completely converted")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["%y"])
+        .fails_with(1)
+        .stderr().is("printf: %y: invalid conversion specification")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["%*d", "99999999999", "1"])
+        .fails_with(1)
+        .stderr().is("printf: invalid field width: '99999999999'")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["%99999999999999999999d", "1"])
+        .fails_with(1)
+        .stderr().is("printf: invalid field width: '99999999999999999999'")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["%.*d", "-99999999999", "1"])
+        .fails_with(1)
+        .stderr().is("printf: invalid precision: '-99999999999'")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["%.2147483648d", "1"])
+        .fails_with(1)
+        .stderr().is("printf: invalid precision: '2147483648'")
+        .unwrap();
+    Assert::main_binary()
+        .fails_with(1)
+        .stderr().contains("printf: missing operand")
+        .unwrap();
+}
@@ -0,0 +1,203 @@
+/*
+ * tail/main.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+extern crate clap;
+extern crate tail;
+
+use clap::{Arg, App};
+use tail::follow::{follow, Watched};
+use tail::*;
+use std::{env, io, process};
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{BufWriter, stdin, stdout, stderr};
+use std::time::Duration;
+
+fn main() -> io::Result<()> {
+    // the obsolete [+-]NUM[bcl][f], as in tail -5, tail +20 or tail -2cf
+    let takes = cli::Obsolete { plus: true, multipliers: "b", flags: "f" };
+    let args = cli::obsolete(env::args().collect(), &takes);
+    let matches = App::new("tail").version(env!("CARGO_PKG_VERSION"))
+        .about("Print the last 10 lines of each FILE to standard output\nReads from stdin if FILE is -, or if there is no FILE")
+        .after_help("NUM may have a multiplier suffix: b 512, kB 1000, K 1024, MB 1000*1000, M 1024*1024,\n\
+                     and so on for G, T, P, E, Z and Y. KiB, MiB and so on are the same as K, M.\n\
+                     -NUM on its own is the same as -n NUM, and +NUM is the same as -n +NUM.")
+        .arg(Arg::with_name("FILE")
+            .help("The file to read")
+            .required(false)
+            .index(1)
+            .multiple(true))
+        .arg(Arg::with_name("bytes")
+            .short("c")
+            .long("bytes")
+            .value_name("[+]NUM")
+            .help("print the last NUM bytes, or with +NUM, everything from byte NUM on")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("lines")
+            .short("n")
+            .long("lines")
+            .value_name("[+]NUM")
+            .help("print the last NUM lines instead of the last 10, or with +NUM, everything from line NUM on")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("f")
+            .short("f")
+            .help("keep printing data as it is appended to the files")
+            .multiple(true))
+        .arg(Arg::with_name("follow")
+            .long("follow")
+            .value_name("HOW")
+            .help("like -f; 'name' follows whatever file has the name, 'descriptor' the file that was opened")
+            .possible_values(&["name", "descriptor"])
+            .min_values(0)
+            .require_equals(true)
+            .takes_value(true))
+        .arg(Arg::with_name("F")
+            .short("F")
+            .help("same as --follow=name --retry")
+            .multiple(true))
+        .arg(Arg::with_name("retry")
+            .long("retry")
+            .help("keep trying to open a file if it is inaccessible"))
+        .arg(Arg::with_name("sleep-interval")
+            .short("s")
+            .long("sleep-interval")
+            .value_name("N")
+            .help("with -f, sleep for about N seconds (default 1.0) between checks")
+            .takes_value(true))
+        .arg(Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .alias("silent")
+            .help("never print headers giving file names")
+            .multiple(true)
+            .overrides_with("verbose"))
+        .arg(Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .help("always print headers giving file names")
+            .multiple(true)
+            .overrides_with("quiet"))
+        .arg(Arg::with_name("zero-terminated")
+            .short("z")
+            .long("zero-terminated")
+            .help("line delimiter is NUL, not newline"))
+        .get_matches_from(&args);
+
+    let mut options: Options = Default::default();
+    let stdin = stdin();
+    let stderr = stderr();
+
+    match cli::count(&matches) {
+        // a leading '+' counts from the start instead of the end
+        Ok(Some(count)) => {
+            options.mode = match (count.unit, count.sign == Some('+')) {
+                (cli::Unit::Lines, false) => Mode::Lines(count.number),
+                (cli::Unit::Lines, true) => Mode::FromLine(count.number),
+                (cli::Unit::Bytes, false) => Mode::Bytes(count.number),
+                (cli::Unit::Bytes, true) => Mode::FromByte(count.number)
+            };
+        },
+        Ok(None) => {},
+        Err(e) => {
+            writeln!(stderr.lock(), "tail: {}", e)?;
+            process::exit(1);
+        }
+    }
+    options.headers = cli::headers(&matches);
+    if matches.is_present("zero-terminated") {
+        options.separator = b'\0';
+    }
+    options.follow = if matches.is_present("F") || matches.value_of("follow") == Some("name") {
+        Some(Follow::Name)
+    } else if matches.is_present("f") || matches.is_present("follow") {
+        Some(Follow::Descriptor)
+    } else {
+        None
+    };
+    options.retry = matches.is_present("retry") || matches.is_present("F");
+    if let Some(seconds) = matches.value_of("sleep-interval") {
+        match seconds.parse::<f64>() {
+            Ok(s) if s >= 0.0 && s.is_finite() => options.sleep = Duration::from_secs_f64(s),
+            _ => {
+                writeln!(stderr.lock(), "tail: invalid number of seconds: '{}'", seconds)?;
+                process::exit(1);
+            }
+        }
+    }
+
+    let files: Vec<&str> = match matches.values_of("FILE") {
+        Some(f) => f.collect(),
+        None => vec!["-"]
+    };
+    let headers = options.headers.wanted(files.len());
+    let mut success = true;
+    let mut first = true;
+    let mut watched: Vec<Watched> = Vec::new();
+    let mut latest = None;
+    let stdout = stdout();
+    let mut output = BufWriter::new(stdout.lock());
+
+    for file in &files {
+        let result = if *file == "-" {
+            if headers {
+                write_header(&mut output, "standard input", first)?;
+            }
+            // a pipe has nothing more to give once it has ended, so it isn't followed
+            latest = None;
+            tail(stdin.lock(), &mut output, &options.mode, options.separator)
+        } else {
+            let mut f = match File::open(file) {
+                Ok(f) => f,
+                Err(e) => {
+                    output.flush()?;
+                    writeln!(stderr.lock(), "tail: cannot open '{}' for reading: {}", file, cli::describe(&e))?;
+                    success = false;
+                    if options.follow.is_some() && options.retry {
+                        watched.push(Watched::new(file, None)?);
+                    }
+                    continue;
+                }
+            };
+            if headers {
+                write_header(&mut output, file, first)?;
+            }
+            let result = tail_file(&mut f, &mut output, &options.mode, options.separator);
+            if result.is_ok() && options.follow.is_some() {
+                latest = Some(watched.len());
+                watched.push(Watched::new(file, Some(f))?);
+            }
+            result
+        };
+        first = false;
+        if let Err(e) = result {
+            cli::exit_if_gone(&e);
+            output.flush()?;
+            let name = if *file == "-" { "standard input" } else { file };
+            writeln!(stderr.lock(), "tail: error reading '{}': {}", name, cli::describe(&e))?;
+            success = false;
+        }
+    }
+    output.flush()?;
+
+    if options.follow.is_some() && files.iter().any(|f| *f != "-") {
+        if let Err(e) = follow(&mut watched, &mut output, &options, headers, latest) {
+            cli::exit_if_gone(&e);
+            writeln!(stderr.lock(), "tail: {}", cli::describe(&e))?;
+        }
+        process::exit(1);
+    }
+    if !success {
+        process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,151 @@
+/*
+ * tail/follow.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+use std::fs::{self, File};
+use std::io::{self, stderr, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::thread;
+use crate::{write_header, Follow, Options};
+
+/// A file being followed, and how far into it has been printed.
+#[derive(Debug)]
+pub struct Watched {
+    /// The name to print in headers and messages
+    pub name: String,
+    pub path: PathBuf,
+    /// The open file, or `None` when it couldn't be opened or has gone away
+    pub file: Option<File>,
+    /// How many bytes of the file have been printed
+    pub position: u64,
+    /// The device and inode of the open file, to tell when the name points somewhere new
+    id: Option<(u64, u64)>
+}
+
+impl Watched {
+    /// Starts watching *file*, which has been read up to its current position.
+    pub fn new(name: &str, file: Option<File>) -> io::Result<Watched> {
+        let mut watched = Watched {
+            name: name.to_owned(),
+            path: PathBuf::from(name),
+            file: None,
+            position: 0,
+            id: None
+        };
+        if let Some(mut f) = file {
+            watched.position = f.stream_position().unwrap_or(0);
+            watched.id = Some(identity(&f.metadata()?));
+            watched.file = Some(f);
+        }
+        Ok(watched)
+    }
+
+    /// Writes anything added to the file since it was last read. Returns whether there was
+    /// anything. A file that has shrunk has been truncated, so it is read again from the start.
+    fn read_more<W: Write>(&mut self, output: &mut W, header: bool) -> io::Result<bool> {
+        let file = match self.file.as_mut() {
+            Some(f) => f,
+            None => return Ok(false)
+        };
+        let metadata = file.metadata()?;
+        if metadata.is_file() {
+            if metadata.len() < self.position {
+                writeln!(stderr(), "tail: {}: file truncated", self.name)?;
+                self.position = file.seek(SeekFrom::Start(0))?;
+            }
+            else if metadata.len() == self.position {
+                return Ok(false);
+            }
+        }
+
+        let mut buffer = [0; 64 * 1024];
+        let mut any = false;
+        loop {
+            let n = file.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            if !any && header {
+                write_header(output, &self.name, false)?;
+            }
+            any = true;
+            output.write_all(&buffer[..n])?;
+            self.position += n as u64;
+        }
+        Ok(any)
+    }
+
+    /// Checks whether the name still leads to the file that is open, and opens the new file
+    /// if it has been replaced, as log rotation does, or has appeared.
+    fn reopen(&mut self) -> io::Result<()> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(m) => m,
+            Err(e) => {
+                if self.file.take().is_some() {
+                    writeln!(stderr(), "tail: '{}' has become inaccessible: {}", self.name, e)?;
+                }
+                self.id = None;
+                return Ok(());
+            }
+        };
+        if self.id == Some(identity(&metadata)) {
+            return Ok(());
+        }
+        if let Ok(f) = File::open(&self.path) {
+            let replaced = self.file.is_some();
+            writeln!(stderr(), "tail: '{}' has {};  following new file",
+                self.name, if replaced { "been replaced" } else { "appeared" })?;
+            self.id = Some(identity(&f.metadata()?));
+            self.file = Some(f);
+            self.position = 0;
+        }
+        Ok(())
+    }
+}
+
+fn identity(metadata: &fs::Metadata) -> (u64, u64) {
+    (metadata.dev(), metadata.ino())
+}
+
+/// Prints whatever has been added to each of the *watched* files since the last time, and
+/// opens any that have appeared, or with `Follow::Name`, been replaced. *last* is the index of
+/// the file printed most recently, for when *headers* are on.
+pub fn catch_up<W: Write>(watched: &mut [Watched], output: &mut W, by_name: bool, headers: bool, last: &mut Option<usize>) -> io::Result<()> {
+    for (i, w) in watched.iter_mut().enumerate() {
+        // whatever was left in the old file comes before the new one
+        if w.read_more(output, headers && *last != Some(i))? {
+            *last = Some(i);
+        }
+        // following the descriptor, a file is only looked for by name until it is first opened,
+        // as with -f --retry
+        if by_name || w.file.is_none() {
+            w.reopen()?;
+            if w.read_more(output, headers && *last != Some(i))? {
+                *last = Some(i);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Keeps printing what is added to the *watched* files, checking every `options.sleep`. When
+/// *headers* are on, a header is printed whenever the output moves to a different file than
+/// *last*, the index of the file printed most recently. This only returns on an error, or when
+/// there is nothing left that could be followed.
+pub fn follow<W: Write>(watched: &mut [Watched], output: &mut W, options: &Options, headers: bool, mut last: Option<usize>) -> io::Result<()> {
+    let by_name = options.follow == Some(Follow::Name);
+    loop {
+        catch_up(watched, output, by_name, headers, &mut last)?;
+        output.flush()?;
+
+        if !options.retry && watched.iter().all(|w| w.file.is_none()) {
+            writeln!(stderr(), "tail: no files remaining")?;
+            return Ok(());
+        }
+        thread::sleep(options.sleep);
+    }
+}
@@ -0,0 +1,175 @@
+/*
+ * tail/lib.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+//!
+//! # **`tail`**
+//!
+//! Print the last part of FILEs (or standard input).
+//!
+//! ```rust
+//! extern crate tail;
+//! ```
+//!
+//! The last 10 lines of each file are printed, with headers as head prints them. -n and -c
+//! choose how many lines or bytes, or with a leading '+', where to start counting from the
+//! beginning. Regular files are read from the end, so only the part that is printed is read.
+//! With -f or -F, the `follow` module keeps printing what is added to the files.
+//!
+//! ```text
+//! $ tail -n 20 app.log
+//! $ tail -c +513 image.bin
+//! $ tail -F /var/log/syslog
+//! ```
+//!
+extern crate head;
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::time::Duration;
+
+pub mod follow;
+
+pub use head::{cli, hold_last_bytes, hold_last_lines, parse_size, skip_bytes, skip_lines, write_header, Headers, SizeError};
+
+/// How much of the end of each file to print.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// The last so many lines: -n NUMBER
+    Lines(u64),
+    /// The last so many bytes: -c NUMBER
+    Bytes(u64),
+    /// Everything from this line on, counting from 1: -n +NUMBER
+    FromLine(u64),
+    /// Everything from this byte on, counting from 1: -c +NUMBER
+    FromByte(u64)
+}
+
+/// How to keep following files once their ends have been printed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Follow {
+    /// Keep reading the file that was opened, even if it is renamed or removed: -f
+    Descriptor,
+    /// Keep reading whatever file has the name, reopening it when it is replaced: -F
+    Name
+}
+
+/// All of the options for tail. These are registered from clap arguments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub mode: Mode,
+    pub headers: Headers,
+    /// What ends a line: newline, or NUL with -z
+    pub separator: u8,
+    /// Whether to wait for more to be written: -f, -F or --follow
+    pub follow: Option<Follow>,
+    /// Keep trying to open files that can't be opened: --retry
+    pub retry: bool,
+    /// How long to wait between checks for more: -s or --sleep-interval
+    pub sleep: Duration
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            mode: Mode::Lines(10),
+            headers: Headers::Auto,
+            separator: b'\n',
+            follow: None,
+            retry: false,
+            sleep: Duration::from_secs(1)
+        }
+    }
+}
+
+/// How much of a file is read at a time when looking for lines from the end.
+const BLOCK: usize = 64 * 1024;
+
+/// Writes the end of *input* to *output*, as much as *mode* asks for. This reads everything,
+/// so it works on pipes; the last lines or bytes are held in memory until the end is reached.
+pub fn tail<R: BufRead, W: Write>(mut input: R, output: &mut W, mode: &Mode, separator: u8) -> io::Result<()> {
+    match *mode {
+        Mode::Lines(k) => {
+            for line in hold_last_lines(input, k, separator, |_| Ok(()))? {
+                output.write_all(&line)?;
+            }
+        },
+        Mode::Bytes(k) => {
+            let held = hold_last_bytes(input, k, |_| Ok(()))?;
+            let (front, back) = held.as_slices();
+            output.write_all(front)?;
+            output.write_all(back)?;
+        },
+        Mode::FromLine(k) => {
+            skip_lines(&mut input, k.saturating_sub(1), separator)?;
+            io::copy(&mut input, output)?;
+        },
+        Mode::FromByte(k) => {
+            skip_bytes(&mut input, k.saturating_sub(1))?;
+            io::copy(&mut input, output)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the end of *file* to *output*. Regular files are read backwards from the end, so
+/// only the part that gets printed is read; anything else, or a regular file that can't be
+/// read that way, goes through `tail()`. Either way *file* is left at the end of what has been
+/// read, ready to be followed.
+pub fn tail_file<W: Write>(file: &mut File, output: &mut W, mode: &Mode, separator: u8) -> io::Result<()> {
+    if !file.metadata()?.is_file() {
+        return tail(BufReader::new(file), output, mode, separator);
+    }
+    // files in /proc and the like say they are empty, and some can't seek to the end at all.
+    // Either way the file is still at the start, to be read through like a pipe.
+    let length = match file.seek(SeekFrom::End(0)) {
+        Ok(0) | Err(_) => return tail(BufReader::new(file), output, mode, separator),
+        Ok(length) => length
+    };
+    let start = match *mode {
+        Mode::Lines(k) => last_lines_start(file, length, k, separator)?,
+        Mode::Bytes(k) => length.saturating_sub(k),
+        Mode::FromByte(k) => k.saturating_sub(1).min(length),
+        Mode::FromLine(_) => {
+            file.seek(SeekFrom::Start(0))?;
+            return tail(BufReader::new(file), output, mode, separator);
+        }
+    };
+    file.seek(SeekFrom::Start(start))?;
+    io::copy(file, output)?;
+    Ok(())
+}
+
+/// Finds where the last *k* lines of a file *length* bytes long start, reading it backwards a
+/// block at a time.
+fn last_lines_start<F: Read + Seek>(file: &mut F, length: u64, k: u64, separator: u8) -> io::Result<u64> {
+    if k == 0 || length == 0 {
+        return Ok(length);
+    }
+    let mut buffer = vec![0; BLOCK];
+    let mut count = 0;
+    // the very last byte ends the last line, even when it is a separator
+    let mut end = length - 1;
+    while end > 0 {
+        let start = end.saturating_sub(BLOCK as u64);
+        let size = (end - start) as usize;
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut buffer[..size])?;
+        for (i, &byte) in buffer[..size].iter().enumerate().rev() {
+            if byte == separator {
+                count += 1;
+                if count == k {
+                    return Ok(start + i as u64 + 1);
+                }
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,120 @@
+/*
+ * tail/tests.rs
+ * Babkock/unix
+ *
+ * Copyright (c) 2019 Tanner Babcock.
+ * MIT License.
+*/
+extern crate assert_cli;
+
+use crate::*;
+use assert_cli::Assert;
+use std::io::Cursor;
+
+#[test]
+fn t_tail() {
+    let cases: &[(&[u8], Mode, u8, &[u8])] = &[
+        (b"1\n2\n3\n", Mode::Lines(2), b'\n', b"2\n3\n"),
+        (b"1\n2\n3", Mode::Lines(1), b'\n', b"3"),
+        (b"1\n2\n3\n", Mode::Lines(0), b'\n', b""),
+        (b"1\n2\n3\n", Mode::Lines(9), b'\n', b"1\n2\n3\n"),
+        (b"1\n2\n3\n", Mode::FromLine(2), b'\n', b"2\n3\n"),
+        (b"1\n2\n3\n", Mode::FromLine(0), b'\n', b"1\n2\n3\n"),
+        (b"abcdef", Mode::Bytes(2), b'\n', b"ef"),
+        (b"abcdef", Mode::FromByte(5), b'\n', b"ef"),
+        (b"a\0b\0c", Mode::Lines(2), b'\0', b"b\0c"),
+    ];
+    for &(text, mode, separator, expected) in cases {
+        let mut output: Vec<u8> = Vec::new();
+        tail(text, &mut output, &mode, separator).unwrap();
+        assert_eq!(output, expected, "{:?}", mode);
+    }
+}
+
+#[test]
+fn t_last_lines_start() {
+    // reading backwards has to get the same answer whichever way the file ends
+    let text = b"one\ntwo\nthree\n";
+    assert_eq!(last_lines_start(&mut Cursor::new(&text[..]), 14, 1, b'\n').unwrap(), 8);
+    assert_eq!(last_lines_start(&mut Cursor::new(&text[..]), 14, 2, b'\n').unwrap(), 4);
+    assert_eq!(last_lines_start(&mut Cursor::new(&text[..]), 14, 5, b'\n').unwrap(), 0);
+    assert_eq!(last_lines_start(&mut Cursor::new(&text[..]), 14, 0, b'\n').unwrap(), 14);
+    assert_eq!(last_lines_start(&mut Cursor::new(&text[..13]), 13, 1, b'\n').unwrap(), 8);
+    assert_eq!(last_lines_start(&mut Cursor::new(&b"\n\n"[..]), 2, 1, b'\n').unwrap(), 1);
+}
+
+#[test]
+fn t_lines_cli() {
+    Assert::main_binary()
+        .with_args(&["-n", "2", "Cargo.toml"])
+        .stdout().is("clap = \"2.33.0\"\nassert_cli = \"0.6.3\"")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["+2", "-q", "Cargo.toml", "Cargo.toml"])
+        .stdout().contains("name = \"tail\"")
+        .stdout().doesnt_contain("==>")
+        .unwrap();
+    Assert::main_binary()
+        .stdin("1\n2\n3\n")
+        .with_args(&["-2c"])
+        .stdout().is("3")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-n", "1", "-v", "-"])
+        .stdin("1\n2\n")
+        .stdout().is("==> standard input <==\n2")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["-n", "-1x", "Cargo.toml"])
+        .fails_with(1)
+        .stderr().is("tail: invalid number of lines: '1x'")
+        .unwrap();
+    Assert::main_binary()
+        .with_args(&["Cargo.toml", "missing"])
+        .fails_with(1)
+        .stderr().is("tail: cannot open 'missing' for reading: No such file or directory")
+        .unwrap();
+}
+
+#[test]
+fn t_follow_cli() {
+    Assert::main_binary()
+        .with_args(&["-f", "no-such-file"])
+        .fails_with(1)
+        .stderr().contains("tail: no files remaining")
+        .unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn t_proc_cli() {
+    // these say they are empty, and can't seek to the end
+    Assert::main_binary()
+        .with_args(&["-n", "2", "/proc/self/status"])
+        .stdout().contains("nonvoluntary_ctxt_switches:")
+        .stdout().doesnt_contain("Name:")
+        .unwrap();
+}
+
+#[test]
+fn t_retry_appeared() {
+    use crate::follow::{catch_up, Watched};
+    use std::{env, fs, process};
+
+    // tail -f --retry on a file that isn't there yet
+    let path = env::temp_dir().join(format!("tail-retry-{}", process::id()));
+    let name = path.to_str().unwrap();
+    let mut watched = vec![Watched::new(name, None).unwrap()];
+    let mut output: Vec<u8> = Vec::new();
+    let mut last = None;
+    catch_up(&mut watched, &mut output, false, false, &mut last).unwrap();
+    assert!(watched[0].file.is_none());
+
+    fs::write(&path, b"one\n").unwrap();
+    catch_up(&mut watched, &mut output, false, false, &mut last).unwrap();
+    fs::write(&path, b"one\ntwo\n").unwrap();
+    catch_up(&mut watched, &mut output, false, false, &mut last).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(output, b"one\ntwo\n");
+    assert_eq!(last, Some(0));
+}